@@ -12,6 +12,10 @@ const CLASH_URL_PREFIX =
   "https://github.com/Dreamacro/clash/releases/download/premium/";
 const CLASH_LATEST_DATE = "2022.01.27";
 
+const META_URL_PREFIX =
+  "https://github.com/MetaCubeX/Clash.Meta/releases/download/";
+const META_VERSION = "v1.10.0";
+
 /**
  * get the correct clash release infomation
  */
@@ -38,26 +42,53 @@ function resolveClash() {
   const exefile = `${name}${isWin ? ".exe" : ""}`;
   const zipfile = `${name}.${zip}`;
 
-  return { url, zip, exefile, zipfile };
+  return { name: "clash", url, zip, exefile, zipfile };
+}
+
+/**
+ * get the correct clash meta release infomation
+ */
+function resolveClashMeta() {
+  const { platform, arch } = process;
+
+  const map = {
+    "win32-x64": "Clash.Meta-windows-amd64",
+    "darwin-x64": "Clash.Meta-darwin-amd64",
+    "darwin-arm64": "Clash.Meta-darwin-arm64",
+    "linux-x64": "Clash.Meta-linux-amd64",
+  };
+
+  const name = map[`${platform}-${arch}`];
+
+  if (!name) {
+    throw new Error(`unsupport platform "${platform}-${arch}"`);
+  }
+
+  const isWin = platform === "win32";
+  const zip = isWin ? "zip" : "gz";
+  const url = `${META_URL_PREFIX}${META_VERSION}/${name}-${META_VERSION}.${zip}`;
+  const exefile = `${name}${isWin ? ".exe" : ""}`;
+  const zipfile = `${name}-${META_VERSION}.${zip}`;
+
+  return { name: "clash-meta", url, zip, exefile, zipfile };
 }
 
 /**
  * get the sidecar bin
  */
-async function resolveSidecar() {
+async function resolveSidecar(binInfo) {
   const sidecarDir = path.join(cwd, "src-tauri", "sidecar");
 
   const host = execSync("rustc -vV | grep host").toString().slice(6).trim();
   const ext = process.platform === "win32" ? ".exe" : "";
-  const sidecarFile = `clash-${host}${ext}`;
+  const sidecarFile = `${binInfo.name}-${host}${ext}`;
   const sidecarPath = path.join(sidecarDir, sidecarFile);
 
   await fs.mkdirp(sidecarDir);
   if (!FORCE && (await fs.pathExists(sidecarPath))) return;
 
   // download sidecar
-  const binInfo = resolveClash();
-  const tempDir = path.join(cwd, "pre-dev-temp");
+  const tempDir = path.join(cwd, `pre-dev-temp-${binInfo.name}`);
   const tempZip = path.join(tempDir, binInfo.zipfile);
   const tempExe = path.join(tempDir, binInfo.exefile);
 
@@ -163,6 +194,7 @@ async function downloadFile(url, path) {
 }
 
 /// main
-resolveSidecar().catch(console.error);
+resolveSidecar(resolveClash()).catch(console.error);
+resolveSidecar(resolveClashMeta()).catch(console.error);
 resolveWintun().catch(console.error);
 resolveMmdb().catch(console.error);
@@ -1,13 +1,49 @@
 use crate::{
-  core::{ClashInfo, PrfItem, PrfOption, Profiles, VergeConfig},
-  states::{ClashState, ProfilesState, VergeState},
-  utils::{dirs, sysopt::SysProxyConfig},
+  core::{
+    clash_api::{
+      self, ClashVersion, ConnectivityResult, CoreStatus, DnsAnswer, ProviderInfo, RuntimeConfig,
+      TrafficTotal,
+    },
+    download_geo_data, Clash, ClashInfo, LaunchStatus, PrfImportResult, PrfItem, PrfOption,
+    PrfSelected, PrfUpdateResult, Profiles, RuleOverrides, VergeConfig,
+  },
+  states::{ClashState, ImportState, LogStreamState, ProfilesState, UpdateState, VergeState},
+  utils::{
+    backup, config, dirs, help, hotkey, resolve,
+    sysopt::{self, SysProxyConfig, SysProxyStatus},
+    tmpl, tray, window,
+  },
 };
-use crate::{ret_err, wrap_err};
+use crate::{log_if_err, ret_err, wrap_err};
 use anyhow::Result;
-use serde_yaml::Mapping;
-use std::{path::PathBuf, process::Command};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use serde_yaml::{Mapping, Value};
+use std::{
+  collections::HashMap,
+  fs,
+  future::Future,
+  path::{Path, PathBuf},
+  process::{Child, Command},
+  time::Duration,
+};
 use tauri::{api, Manager, State};
+use tokio::sync::oneshot;
+
+/// the time to wait for the editor to fail
+const EDITOR_CHECK_DELAY: Duration = Duration::from_millis(300);
+
+/// the hosted dashboard used by default
+const DEFAULT_DASHBOARD: &str = "https://yacd.haishan.me/";
+
+/// the error code when deleting the current profile without `force`
+const DELETE_CURRENT_PROFILE: &str = "DELETE_CURRENT_PROFILE";
+
+/// the error code when the fetch is canceled by `cancel_update`
+const UPDATE_CANCELED: &str = "UPDATE_CANCELED";
+
+/// the time to wait for the enhancement by the window
+/// the script would be terminated in 5s by the window
+const ENHANCE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// get all profiles from `profiles.yaml`
 #[tauri::command]
@@ -18,23 +54,179 @@ pub fn get_profiles<'a>(profiles_state: State<'_, ProfilesState>) -> Result<Prof
 
 /// synchronize data irregularly
 #[tauri::command]
-pub fn sync_profiles(profiles_state: State<'_, ProfilesState>) -> Result<(), String> {
+pub fn sync_profiles(
+  app_handle: tauri::AppHandle,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
   let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(profiles.sync_file())
+  wrap_err!(profiles.sync_file())?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
 }
 
 /// import the profile from url
 /// and save to `profiles.yaml`
+/// select it as the current one if `auto_select`
 #[tauri::command]
 pub async fn import_profile(
   url: String,
   option: Option<PrfOption>,
+  auto_select: Option<bool>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+  update_state: State<'_, UpdateState>,
+) -> Result<(), String> {
+  // the data has been validated here
+  let fetch = async { wrap_err!(PrfItem::from_url(&url, None, None, option).await) };
+  let item = cancelable(&url, &update_state, fetch).await?;
+  let uid = item.uid.clone();
+
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.append_item(item))?;
+
+  if auto_select.unwrap_or(false) {
+    if let Some(uid) = uid {
+      wrap_err!(profiles.put_current(uid))?;
+
+      let clash = clash_state.0.lock().unwrap();
+      wrap_err!(clash.activate(&profiles, false))?;
+    }
+  }
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
+}
+
+/// import the profile from the qr code image
+/// the content may be the subscription url, the `clash://` link or the profile text
+#[tauri::command]
+pub async fn import_from_qr(
+  image_path: String,
+  option: Option<PrfOption>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+  update_state: State<'_, UpdateState>,
+) -> Result<(), String> {
+  let content = wrap_err!(help::decode_qr_image(Path::new(&image_path)))?;
+
+  let url = match help::parse_import_link(&content) {
+    Some(url) => Some(url),
+    None if content.starts_with("http://") || content.starts_with("https://") => {
+      Some(content.clone())
+    }
+    None => None,
+  };
+
+  match url {
+    Some(url) => {
+      import_profile(
+        url,
+        option,
+        None,
+        app_handle,
+        clash_state,
+        profiles_state,
+        update_state,
+      )
+      .await
+    }
+    None => import_profile_text(content, None, app_handle, profiles_state),
+  }
+}
+
+/// take the url of the deep link waiting for the confirmation
+#[tauri::command]
+pub fn take_pending_import(import_state: State<'_, ImportState>) -> Result<Option<String>, String> {
+  Ok(import_state.0.lock().unwrap().take())
+}
+
+/// take the error of the launch on setup
+/// the window is not loaded to receive the notice then
+#[tauri::command]
+pub fn take_launch_error(clash_state: State<'_, ClashState>) -> Result<Option<String>, String> {
+  Ok(clash_state.0.lock().unwrap().launch_error.take())
+}
+
+/// import the profiles from the list url
+/// report the result of each entry instead of aborting
+#[tauri::command]
+pub async fn import_profiles_batch(
+  url: String,
+  option: Option<PrfOption>,
+  app_handle: tauri::AppHandle,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<Vec<PrfImportResult>, String> {
+  let list = wrap_err!(PrfItem::fetch_list(&url, option.as_ref()).await)?;
+  let mut results = vec![];
+
+  for entry in list.into_iter() {
+    // the entry's option takes precedence
+    let item_option = PrfOption::merge(option.clone(), entry.option);
+    let name = entry.name.clone();
+
+    let result = match PrfItem::from_url(&entry.url, entry.name, None, item_option).await {
+      Ok(item) => {
+        let uid = item.uid.clone();
+        let mut profiles = profiles_state.0.lock().unwrap();
+        profiles.append_item(item).map(|_| uid)
+      }
+      Err(err) => Err(err),
+    };
+
+    if let Err(err) = result.as_ref() {
+      log::error!("failed to import the profile \"{}\" for {err}", entry.url);
+    }
+
+    results.push(PrfImportResult {
+      url: entry.url,
+      name,
+      uid: result.as_ref().ok().cloned().flatten(),
+      error: result.err().map(|err| err.to_string()),
+    });
+  }
+
+  let profiles = profiles_state.0.lock().unwrap();
+  tray::update_menu(&app_handle, &profiles);
+
+  Ok(results)
+}
+
+/// import the profile from the raw config text
+/// save it as a `local` profile
+#[tauri::command]
+pub fn import_profile_text(
+  text: String,
+  name: Option<String>,
+  app_handle: tauri::AppHandle,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let item = wrap_err!(PrfItem::from_text(name, None, text))?;
+
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.append_item(item))?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
+}
+
+/// import the profile from the local file
+/// the content is copied as a `local` profile
+#[tauri::command]
+pub fn import_profile_file(
+  path: String,
+  app_handle: tauri::AppHandle,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
-  let item = wrap_err!(PrfItem::from_url(&url, None, None, option).await)?;
+  let item = wrap_err!(PrfItem::from_file(&path))?;
 
   let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(profiles.append_item(item))
+  wrap_err!(profiles.append_item(item))?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
 }
 
 /// new a profile
@@ -43,12 +235,16 @@ pub async fn import_profile(
 #[tauri::command]
 pub async fn create_profile(
   item: PrfItem, // partial
+  app_handle: tauri::AppHandle,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
   let item = wrap_err!(PrfItem::from(item).await)?;
   let mut profiles = profiles_state.0.lock().unwrap();
 
-  wrap_err!(profiles.append_item(item))
+  wrap_err!(profiles.append_item(item))?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
 }
 
 /// Update the profile
@@ -58,199 +254,1154 @@ pub async fn update_profile(
   option: Option<PrfOption>,
   clash_state: State<'_, ClashState>,
   profiles_state: State<'_, ProfilesState>,
+  update_state: State<'_, UpdateState>,
 ) -> Result<(), String> {
-  let (url, opt) = {
-    // must release the lock here
+  update_item(&index, option, &profiles_state, &update_state).await?;
+
+  // reactivate the profile
+  let profiles = profiles_state.0.lock().unwrap();
+  if Some(index) == profiles.get_current() {
+    let clash = clash_state.0.lock().unwrap();
+    wrap_err!(clash.activate(&profiles, false))?;
+  }
+
+  Ok(())
+}
+
+/// update all the remote profiles one by one
+/// the failed ones would not abort the others
+/// the pinned ones are skipped
+#[tauri::command]
+pub async fn update_all_profiles(
+  option: Option<PrfOption>,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+  update_state: State<'_, UpdateState>,
+) -> Result<Vec<PrfUpdateResult>, String> {
+  let (list, current) = {
     let profiles = profiles_state.0.lock().unwrap();
-    let item = wrap_err!(profiles.get_item(&index))?;
+    (profiles.get_update_all_list(), profiles.get_current())
+  };
 
-    // check the profile type
-    if let Some(typ) = item.itype.as_ref() {
-      if *typ != "remote" {
-        ret_err!(format!("could not update the `{typ}` profile"));
-      }
+  let mut results = vec![];
+  let mut current_changed = false;
+
+  for uid in list.into_iter() {
+    let result = update_item(&uid, option.clone(), &profiles_state, &update_state).await;
+
+    if let Err(err) = result.as_ref() {
+      log::error!("failed to update the profile \"{uid}\" for {err}");
+    }
+
+    if result.is_ok() && Some(&uid) == current.as_ref() {
+      current_changed = true;
+    }
+
+    let name = {
+      let profiles = profiles_state.0.lock().unwrap();
+      profiles
+        .get_item(&uid)
+        .ok()
+        .and_then(|item| item.name.clone())
+    };
+
+    results.push(PrfUpdateResult {
+      uid,
+      name,
+      error: result.err(),
+    });
+  }
+
+  // reactivate the profile only once
+  if current_changed {
+    let profiles = profiles_state.0.lock().unwrap();
+    let clash = clash_state.0.lock().unwrap();
+    wrap_err!(clash.activate(&profiles, false))?;
+  }
+
+  Ok(results)
+}
+
+/// fetch and save the profile without activating
+/// record the error in the list if it failed
+async fn update_item(
+  index: &String,
+  option: Option<PrfOption>,
+  profiles_state: &State<'_, ProfilesState>,
+  update_state: &State<'_, UpdateState>,
+) -> Result<(), String> {
+  let fetch = fetch_profile(index, option, profiles_state);
+
+  let item = match cancelable(index, update_state, fetch).await {
+    Ok(item) => item,
+    Err(err) if err == UPDATE_CANCELED => return Err(err),
+    Err(err) => {
+      // keep the old file and show the error in the list
+      let mut profiles = profiles_state.0.lock().unwrap();
+      log_if_err!(profiles.set_item_error(index, err.clone()));
+      return Err(err);
+    }
+  };
+
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.update_item(index.clone(), item))
+}
+
+/// cancel the running update or import
+/// the index is the profile uid or the import url
+/// the old profile file is kept
+#[tauri::command]
+pub fn cancel_update(index: String, update_state: State<'_, UpdateState>) -> Result<(), String> {
+  let sender = update_state.0.lock().unwrap().remove(&index);
+
+  match sender {
+    Some(sender) => {
+      let _ = sender.send(());
+      Ok(())
+    }
+    None => ret_err!(format!("no running update for \"{index}\"")),
+  }
+}
+
+/// run the fetch until it finishes or `cancel_update` is called
+async fn cancelable<T>(
+  index: &String,
+  update_state: &State<'_, UpdateState>,
+  fetch: impl Future<Output = Result<T, String>>,
+) -> Result<T, String> {
+  let (sender, receiver) = oneshot::channel();
+  update_state.0.lock().unwrap().insert(index.clone(), sender);
+
+  // ignore the sender replaced by another fetch of the same index
+  let result = tokio::select! {
+    result = fetch => result,
+    Ok(_) = receiver => Err(UPDATE_CANCELED.into()),
+  };
+
+  // remove the finished ones only
+  let mut senders = update_state.0.lock().unwrap();
+  senders.retain(|_, sender| !sender.is_closed());
+
+  result
+}
+
+/// fetch the latest data of the profile
+async fn fetch_profile(
+  index: &String,
+  option: Option<PrfOption>,
+  profiles_state: &State<'_, ProfilesState>,
+) -> Result<PrfItem, String> {
+  let src_path = {
+    let profiles = profiles_state.0.lock().unwrap();
+    let item = wrap_err!(profiles.get_item(index))?;
+
+    match item.itype.as_deref() {
+      Some("file-link") => item.src_path.clone(),
+      _ => None,
+    }
+  };
+
+  match src_path {
+    // the linked file would be read on activation
+    // only check it here
+    Some(src_path) => {
+      wrap_err!(PrfItem::read_file_link(&src_path))?;
+
+      Ok(PrfItem {
+        updated: Some(help::get_now()),
+        ..PrfItem::default()
+      })
     }
+    None => {
+      let (url, opt) = {
+        // must release the lock here
+        let profiles = profiles_state.0.lock().unwrap();
+        let item = wrap_err!(profiles.get_item(index))?;
+
+        // check the profile type
+        if let Some(typ) = item.itype.as_ref() {
+          if *typ != "remote" {
+            ret_err!(format!("could not update the `{typ}` profile"));
+          }
+        }
+
+        if item.url.is_none() {
+          ret_err!("failed to get the item url");
+        }
 
-    if item.url.is_none() {
-      ret_err!("failed to get the item url");
+        (item.url.clone().unwrap(), item.option.clone())
+      };
+
+      let fetch_opt = PrfOption::merge(opt, option);
+      let fetch_opt = wrap_err!(PrfItem::restore_basic_auth(index, fetch_opt))?;
+      wrap_err!(PrfItem::from_url(&url, None, None, fetch_opt).await)
     }
+  }
+}
+
+/// change the current profile
+#[tauri::command]
+pub fn select_profile(
+  index: String,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.put_current(index))?;
+
+  tray::update_menu(&app_handle, &profiles);
+
+  let clash = clash_state.0.lock().unwrap();
+  wrap_err!(clash.activate(&profiles, false))
+}
+
+/// reorder the profiles by the uid list
+#[tauri::command]
+pub fn reorder_profiles(
+  uids: Vec<String>,
+  app_handle: tauri::AppHandle,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.reorder(uids))?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
+}
+
+/// change the profile chain
+#[tauri::command]
+pub fn change_profile_chain(
+  chain: Option<Vec<String>>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  wrap_err!(profiles.put_chain(chain))?;
+  clash.set_window(app_handle.get_window("main"));
+
+  wrap_err!(clash.activate_enhanced(&profiles, false))
+}
+
+/// move the item of the chain up or down by one step
+#[tauri::command]
+pub fn move_profile_chain(
+  uid: String,
+  up: bool,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  wrap_err!(profiles.move_chain(&uid, up))?;
+  clash.set_window(app_handle.get_window("main"));
+
+  wrap_err!(clash.activate_enhanced(&profiles, false))
+}
+
+/// add a rule to the runtime overrides
+/// the position should be `prepend` or `append`
+#[tauri::command]
+pub fn add_rule(
+  rule: String,
+  position: Option<String>,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let clash = clash_state.0.lock().unwrap();
+  let profiles = profiles_state.0.lock().unwrap();
+
+  let mut overrides = RuleOverrides::new();
+  wrap_err!(overrides.add_rule(rule, position))?;
+  wrap_err!(clash.activate(&profiles, false))
+}
+
+/// get the runtime overrides
+#[tauri::command]
+pub fn get_rule_overrides() -> Result<RuleOverrides, String> {
+  Ok(RuleOverrides::new())
+}
+
+/// remove all the runtime overrides
+#[tauri::command]
+pub fn clear_rule_overrides(
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let clash = clash_state.0.lock().unwrap();
+  let profiles = profiles_state.0.lock().unwrap();
+
+  let mut overrides = RuleOverrides::new();
+  wrap_err!(overrides.clear())?;
+  wrap_err!(clash.activate(&profiles, false))
+}
+
+/// route the domain directly by the runtime overrides
+#[tauri::command]
+pub fn add_bypass_domain(
+  domain: String,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let clash = clash_state.0.lock().unwrap();
+  let profiles = profiles_state.0.lock().unwrap();
+
+  let mut overrides = RuleOverrides::new();
+  wrap_err!(overrides.add_bypass_domain(domain))?;
+  wrap_err!(clash.activate(&profiles, false))
+}
+
+/// remove the domain from the runtime overrides
+#[tauri::command]
+pub fn remove_bypass_domain(
+  domain: String,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let clash = clash_state.0.lock().unwrap();
+  let profiles = profiles_state.0.lock().unwrap();
+
+  let mut overrides = RuleOverrides::new();
+  wrap_err!(overrides.remove_bypass_domain(domain))?;
+  wrap_err!(clash.activate(&profiles, false))
+}
+
+/// get the domains routed directly
+#[tauri::command]
+pub fn list_bypass_domains() -> Result<Vec<String>, String> {
+  Ok(RuleOverrides::new().bypass_domains.unwrap_or_default())
+}
+
+/// manually exec enhanced profile
+#[tauri::command]
+pub fn enhance_profiles(
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut clash = clash_state.0.lock().unwrap();
+  let profiles = profiles_state.0.lock().unwrap();
+
+  clash.set_window(app_handle.get_window("main"));
+
+  wrap_err!(clash.activate_enhanced(&profiles, false))
+}
+
+/// export the final generated clash config
+/// save it to the path if given and return the yaml
+#[tauri::command]
+pub async fn export_config(
+  path: Option<String>,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<String, String> {
+  let config = run_enhance(|sender| {
+    let clash = clash_state.0.lock().unwrap();
+    let profiles = profiles_state.0.lock().unwrap();
+
+    wrap_err!(clash.gen_enhanced_config(&profiles, false, move |config| {
+      let _ = sender.send(Ok(config));
+    }))
+  })
+  .await?;
+
+  let config = Clash::runtime_config(config, &VergeConfig::new());
+  let yaml = wrap_err!(serde_yaml::to_string(&config))?;
+
+  if let Some(path) = path {
+    if let Err(err) = fs::write(&path, yaml.as_bytes()) {
+      ret_err!(format!("failed to save the config to \"{path}\" for {err}"));
+    }
+  }
+
+  Ok(yaml)
+}
+
+/// start the enhancement by the window and wait for the result
+/// the locks taken by `start` are released before waiting
+async fn run_enhance<F>(start: F) -> Result<Mapping, String>
+where
+  F: FnOnce(oneshot::Sender<Result<Mapping>>) -> Result<(), String>,
+{
+  let (sender, receiver) = oneshot::channel();
+  start(sender)?;
+
+  match tokio::time::timeout(ENHANCE_TIMEOUT, receiver).await {
+    Ok(Ok(result)) => wrap_err!(result),
+    _ => ret_err!("failed to get the enhanced config"),
+  }
+}
+
+/// load the profile into the core to find the errors
+/// then revert to the current profile
+#[tauri::command]
+pub async fn dry_run_profile(
+  index: String,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  let config = run_enhance(|sender| {
+    let clash = clash_state.0.lock().unwrap();
+    let profiles = profiles_state.0.lock().unwrap();
+    let profiles = wrap_err!(profiles.with_current(index))?;
+
+    wrap_err!(clash.gen_enhanced_config(&profiles, false, move |config| {
+      let _ = sender.send(Ok(config));
+    }))
+  })
+  .await?;
+
+  let config = Clash::runtime_config(config, &VergeConfig::new());
+  let path = dirs::dry_run_temp_path();
+  wrap_err!(config::save_yaml(path.clone(), &config, None))?;
+
+  let result = clash_api::put_configs(&info, &path).await;
+  let _ = fs::remove_file(&path);
+
+  // revert it whether it succeeds or not
+  {
+    let clash = clash_state.0.lock().unwrap();
+    let profiles = profiles_state.0.lock().unwrap();
+    log_if_err!(clash.activate(&profiles, false));
+  }
+
+  wrap_err!(result)
+}
+
+/// test the enhanced script with the current profile
+/// return the result config or the error
+#[tauri::command]
+pub async fn test_enhance_script(
+  script: String,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<Mapping, String> {
+  run_enhance(|sender| {
+    let clash = clash_state.0.lock().unwrap();
+    let profiles = profiles_state.0.lock().unwrap();
+
+    wrap_err!(clash.test_script(&profiles, script, move |result| {
+      let _ = sender.send(result);
+    }))
+  })
+  .await
+}
+
+/// delete profile item
+/// the current profile requires `force` to be deleted
+#[tauri::command]
+pub fn delete_profile(
+  index: String,
+  force: Option<bool>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  // let the frontend confirm before deleting the profile in use
+  if profiles.get_current() == Some(index.clone()) && !force.unwrap_or(false) {
+    ret_err!(DELETE_CURRENT_PROFILE);
+  }
+
+  let delete_current = wrap_err!(profiles.delete_item(index))?;
+  tray::update_menu(&app_handle, &profiles);
+
+  if delete_current {
+    let clash = clash_state.0.lock().unwrap();
+    wrap_err!(clash.activate(&profiles, false))?;
+  }
+
+  Ok(())
+}
+
+/// patch the profile config
+#[tauri::command]
+pub fn patch_profile(
+  index: String,
+  profile: PrfItem,
+  app_handle: tauri::AppHandle,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.patch_item(index, profile))?;
+
+  tray::update_menu(&app_handle, &profiles);
+  Ok(())
+}
+
+/// remember the selected proxy of the group for the profile
+/// other groups and profiles are not touched
+#[tauri::command]
+pub fn patch_selected(
+  index: String,
+  selected: PrfSelected,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.set_selected(&index, vec![selected]))
+}
+
+/// open the profile with the editor
+/// use the `default_editor` first, then vscode, then the os default
+#[tauri::command]
+pub async fn view_profile(
+  index: String,
+  profiles_state: State<'_, ProfilesState>,
+  verge_state: State<'_, VergeState>,
+) -> Result<(), String> {
+  let editor = {
+    let verge = verge_state.0.lock().unwrap();
+    verge.config.default_editor.clone()
+  };
+
+  // the profile files are written under the lock
+  // hold it until the editor is spawned to avoid opening a partial file
+  let child = {
+    let profiles = profiles_state.0.lock().unwrap();
+    let path = profile_path(&profiles, &index)?;
+    spawn_editor(editor, path)?
+  };
+
+  let child = match child {
+    Some(child) => child,
+    None => return Ok(()),
+  };
+  wrap_err!(wait_editor(child).await)?;
+
+  // the statistics of the edited file
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(profiles.refresh_item_stats(&index))
+}
+
+/// spawn the editor to open the file
+/// none if it is opened by the os default
+fn spawn_editor(editor: Option<String>, path: PathBuf) -> Result<Option<Child>, String> {
+  // the invalid editor should fall through
+  if let Some(editor) = editor.filter(|e| !e.trim().is_empty()) {
+    match which::which(editor.trim()) {
+      Ok(editor) => match open_editor_cmd(&editor, &path) {
+        Ok(child) => return Ok(Some(child)),
+        Err(err) => log::warn!("failed to open file by {:?} for {err}", editor),
+      },
+      Err(err) => log::warn!("failed to find the editor \"{editor}\" for {err}"),
+    }
+  }
+
+  // use vscode first
+  if let Ok(code) = which::which("code") {
+    return match open_editor_cmd(&code, &path) {
+      Ok(child) => Ok(Some(child)),
+      Err(err) => {
+        log::error!("{err}");
+        Err("failed to open file by VScode".into())
+      }
+    };
+  }
+
+  open_path_cmd(path, "failed to open file by `open`").map(|_| None)
+}
+
+/// open the file with the editor
+fn open_editor_cmd(editor: &Path, path: &Path) -> std::io::Result<Child> {
+  let mut command = Command::new(editor);
+
+  #[cfg(target_os = "windows")]
+  {
+    use std::os::windows::process::CommandExt;
+    command.creation_flags(0x08000000);
+  }
+
+  command.arg(path).spawn()
+}
+
+/// the editor may exit at once if the file is locked
+/// the launchers like `code` exit successfully after handing off the file
+async fn wait_editor(mut child: Child) -> Result<()> {
+  tokio::time::sleep(EDITOR_CHECK_DELAY).await;
+
+  match child.try_wait()? {
+    Some(status) if !status.success() => {
+      anyhow::bail!("the editor exited immediately with {status}, the file may be locked")
+    }
+    _ => Ok(()),
+  }
+}
+
+/// open the file manager with the profile file selected
+#[tauri::command]
+pub fn reveal_profile(
+  index: String,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let path = {
+    let profiles = profiles_state.0.lock().unwrap();
+    profile_path(&profiles, &index)?
+  };
+
+  reveal_path_cmd(path, "failed to reveal the profile")
+}
+
+/// get the existing file path of the profile
+fn profile_path(profiles: &Profiles, index: &String) -> Result<PathBuf, String> {
+  let item = wrap_err!(profiles.get_item(index))?;
+
+  let path = match item.file_path() {
+    Some(path) => path,
+    None => ret_err!("the file is null"),
+  };
+
+  if !path.exists() {
+    ret_err!("the file not found");
+  }
+
+  Ok(path)
+}
+
+/// restart the sidecar
+/// the system proxy follows the ports picked by the new core
+#[tauri::command]
+pub fn restart_sidecar(
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  wrap_err!(clash.restart_sidecar(&mut profiles))?;
+  wrap_err!(verge.update_port(clash.info.port.clone()))
+}
+
+/// update the geo databases and restart the core to load them
+/// the old databases are kept if failed
+#[tauri::command]
+pub async fn update_geo_data(
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<Vec<String>, String> {
+  let (source, core) = {
+    let verge = verge_state.0.lock().unwrap();
+    let config = &verge.config;
+    (config.geo_data_url.clone(), config.core.clone())
+  };
+
+  let updated = wrap_err!(download_geo_data(source, core).await)?;
+
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(clash.restart_sidecar(&mut profiles))?;
+  wrap_err!(verge.update_port(clash.info.port.clone()))?;
+
+  Ok(updated)
+}
+
+/// get the clash core info from the state
+/// the caller can also get the infomation by clash's api
+#[tauri::command]
+pub fn get_clash_info(clash_state: State<'_, ClashState>) -> Result<ClashInfo, String> {
+  let clash = clash_state.0.lock().unwrap();
+  Ok(clash.info.clone())
+}
+
+/// check whether the secret is accepted by the running core
+#[tauri::command]
+pub async fn test_clash_secret(
+  secret: Option<String>,
+  clash_state: State<'_, ClashState>,
+) -> Result<bool, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    ClashInfo {
+      secret,
+      ..clash.info.clone()
+    }
+  };
+
+  Ok(clash_api::ping(&info).await)
+}
+
+/// reload the generated config from the disk
+/// faster than restarting the core
+#[tauri::command]
+pub async fn reload_clash_config(clash_state: State<'_, ClashState>) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  let path = dirs::profiles_temp_path();
+  if !path.exists() {
+    ret_err!("the runtime config does not exist");
+  }
+
+  wrap_err!(clash_api::put_configs(&info, &path).await)
+}
+
+/// get the absolute path of the runtime config loaded by the core
+/// it is regenerated on every activation
+#[tauri::command]
+pub fn get_runtime_config_path() -> Result<String, String> {
+  let path = dirs::profiles_temp_path();
+  if !path.exists() {
+    ret_err!("the runtime config does not exist");
+  }
+
+  let path = wrap_err!(dunce::canonicalize(path))?;
+  Ok(path.to_string_lossy().to_string())
+}
+
+/// get the clash core version by clash's api
+#[tauri::command]
+pub async fn get_clash_version(clash_state: State<'_, ClashState>) -> Result<ClashVersion, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::get_version(&info).await)
+}
+
+/// get whether the core is running and responsive
+#[tauri::command]
+pub async fn get_core_status(clash_state: State<'_, ClashState>) -> Result<CoreStatus, String> {
+  let (running, info) = {
+    let clash = clash_state.0.lock().unwrap();
+    (clash.sidecar.is_some(), clash.info.clone())
+  };
+
+  let responsive = running && clash_api::ping(&info).await;
+
+  Ok(CoreStatus {
+    running,
+    port: info.port,
+    responsive,
+  })
+}
+
+/// get the total traffic by clash's api
+/// the realtime traffic is pushed by the `verge://traffic` event
+#[tauri::command]
+pub async fn get_traffic(clash_state: State<'_, ClashState>) -> Result<TrafficTotal, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::get_traffic_total(&info).await)
+}
+
+/// get the active connections by clash's api
+#[tauri::command]
+pub async fn get_connections(
+  clash_state: State<'_, ClashState>,
+) -> Result<serde_json::Value, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::get_connections(&info).await)
+}
+
+/// close the connection by id
+#[tauri::command]
+pub async fn close_connection(
+  id: String,
+  clash_state: State<'_, ClashState>,
+) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::close_connections(&info, Some(&id)).await)
+}
+
+/// close all the connections
+#[tauri::command]
+pub async fn close_all_connections(clash_state: State<'_, ClashState>) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::close_connections(&info, None).await)
+}
+
+/// get the proxy and rule providers of the running core
+#[tauri::command]
+pub async fn get_providers(
+  clash_state: State<'_, ClashState>,
+) -> Result<Vec<ProviderInfo>, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::get_providers(&info).await)
+}
+
+/// refresh the provider by name
+#[tauri::command]
+pub async fn update_provider(
+  name: String,
+  clash_state: State<'_, ClashState>,
+) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  let providers = wrap_err!(clash_api::get_providers(&info).await)?;
+
+  match providers.iter().find(|p| p.name == name) {
+    Some(provider) => wrap_err!(clash_api::update_provider(&info, provider).await),
+    None => ret_err!(format!("failed to find the provider \"{name}\"")),
+  }
+}
+
+/// check whether the traffic is actually proxied
+/// request the url through the clash port and directly
+#[tauri::command]
+pub async fn check_connectivity(
+  url: Option<String>,
+  clash_state: State<'_, ClashState>,
+) -> Result<ConnectivityResult, String> {
+  let port = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.port.clone()
+  };
+
+  let port = match port {
+    Some(port) => port,
+    None => ret_err!("failed to get the clash port"),
+  };
+
+  let url = url.unwrap_or(clash_api::DELAY_TEST_URL.into());
+
+  let (proxy, direct) = tokio::join!(
+    clash_api::check_connectivity(&url, Some(&port)),
+    clash_api::check_connectivity(&url, None)
+  );
+
+  Ok(ConnectivityResult { proxy, direct })
+}
+
+/// flush the fakeip cache of the core
+/// also flush the dns cache of the os if `dns`
+/// only warn if not supported
+#[tauri::command]
+pub async fn flush_fakeip(
+  dns: Option<bool>,
+  clash_state: State<'_, ClashState>,
+) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  if let Err(err) = clash_api::flush_fakeip(&info).await {
+    log::warn!("{err}");
+  }
+
+  if dns.unwrap_or(false) {
+    if let Err(err) = sysopt::flush_dns() {
+      log::warn!("{err}");
+    }
+  }
+
+  Ok(())
+}
+
+/// query the dns answers of the domain by the core
+/// the `record_type` defaults to `A`
+#[tauri::command]
+pub async fn query_dns(
+  domain: String,
+  record_type: Option<String>,
+  clash_state: State<'_, ClashState>,
+) -> Result<Vec<DnsAnswer>, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  let record_type = record_type.unwrap_or("A".into());
+  wrap_err!(clash_api::query_dns(&info, domain.trim(), &record_type).await)
+}
+
+/// test the delay of all the proxies in the group
+/// return the map of proxy name to delay(ms)
+#[tauri::command]
+pub async fn test_group_delay(
+  group: String,
+  url: Option<String>,
+  timeout: Option<u64>,
+  clash_state: State<'_, ClashState>,
+) -> Result<HashMap<String, Option<u64>>, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  let url = url.unwrap_or(clash_api::DELAY_TEST_URL.into());
+  let timeout = timeout.unwrap_or(clash_api::DELAY_TEST_TIMEOUT);
+
+  wrap_err!(clash_api::get_group_delay(&info, &group, &url, timeout).await)
+}
+
+/// open the dashboard with the clash external controller
+/// use the `dashboard_url` in the verge config
+#[tauri::command]
+pub fn open_dashboard(
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+) -> Result<(), String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+  let dashboard = {
+    let verge = verge_state.0.lock().unwrap();
+    verge.config.dashboard_url.clone()
+  };
 
-    (item.url.clone().unwrap(), item.option.clone())
+  let server = match info.server.as_ref() {
+    Some(server) => server,
+    None => ret_err!("failed to get the clash external controller"),
   };
 
-  let fetch_opt = PrfOption::merge(opt, option);
-  let item = wrap_err!(PrfItem::from_url(&url, None, None, fetch_opt).await)?;
+  let (host, port) = server.rsplit_once(':').unwrap_or((server.as_str(), "80"));
+  let secret = info.secret.unwrap_or_default();
+  let encode = |s: &str| utf8_percent_encode(s, NON_ALPHANUMERIC).to_string();
 
-  let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(profiles.update_item(index.clone(), item))?;
+  let dashboard = dashboard
+    .filter(|d| !d.trim().is_empty())
+    .unwrap_or(DEFAULT_DASHBOARD.into());
+  let sep = if dashboard.contains('?') { "&" } else { "?" };
 
-  // reactivate the profile
-  if Some(index) == profiles.get_current() {
-    let clash = clash_state.0.lock().unwrap();
-    wrap_err!(clash.activate(&profiles, false))?;
-  }
+  let url = format!(
+    "{dashboard}{sep}hostname={}&port={}&secret={}",
+    encode(host),
+    encode(port),
+    encode(&secret)
+  );
 
-  Ok(())
+  open_path_cmd(PathBuf::from(url), "failed to open the dashboard")
 }
 
-/// change the current profile
+/// open the home page of the profile provider
 #[tauri::command]
-pub fn select_profile(
+pub fn open_profile_home(
   index: String,
-  clash_state: State<'_, ClashState>,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
-  let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(profiles.put_current(index))?;
+  let home = {
+    let profiles = profiles_state.0.lock().unwrap();
+    let item = wrap_err!(profiles.get_item(&index))?;
+    item.home.clone()
+  };
 
-  let clash = clash_state.0.lock().unwrap();
-  wrap_err!(clash.activate(&profiles, false))
+  match home.filter(|h| h.starts_with("http://") || h.starts_with("https://")) {
+    Some(home) => open_path_cmd(PathBuf::from(home), "failed to open the home page"),
+    None => ret_err!("the profile has no valid home page"),
+  }
 }
 
-/// change the profile chain
+/// update the clash core config
+/// after putting the change to the clash core
+/// then we should save the latest config
 #[tauri::command]
-pub fn change_profile_chain(
-  chain: Option<Vec<String>>,
+pub fn patch_clash_config(
+  payload: Mapping,
   app_handle: tauri::AppHandle,
   clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
+  let mode = payload
+    .get(&Value::from("mode"))
+    .and_then(|m| m.as_str())
+    .map(|m| m.to_string());
+
   let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
   let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(clash.patch_config(payload, &mut verge, &mut profiles))?;
 
-  profiles.put_chain(chain);
-  clash.set_window(app_handle.get_window("main"));
+  // sync the tray checkmark
+  if let Some(mode) = mode {
+    tray::update_mode(&app_handle, &mode);
+  }
 
-  wrap_err!(clash.activate_enhanced(&profiles, false))
+  Ok(())
 }
 
-/// manually exec enhanced profile
+/// list the network interfaces for the `interface-name`
 #[tauri::command]
-pub fn enhance_profiles(
+pub fn get_interfaces() -> Result<Vec<String>, String> {
+  wrap_err!(help::interface_names())
+}
+
+/// list the local networks for the bypass suggestions
+/// the down interfaces are only skipped on linux
+/// the others may list the networks of the disconnected ones
+#[tauri::command]
+pub fn get_local_networks() -> Result<Vec<String>, String> {
+  wrap_err!(help::local_networks())
+}
+
+/// reject all the traffic by the core instead of falling back to direct
+/// the state is persisted so that a restart does not leak
+#[tauri::command]
+pub fn set_kill_switch(
+  enable: bool,
   app_handle: tauri::AppHandle,
   clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
   let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
   let profiles = profiles_state.0.lock().unwrap();
 
-  clash.set_window(app_handle.get_window("main"));
+  wrap_err!(verge.patch_config(VergeConfig {
+    enable_kill_switch: Some(enable),
+    ..VergeConfig::default()
+  }))?;
 
-  wrap_err!(clash.activate_enhanced(&profiles, false))
+  clash.set_window(app_handle.get_window("main"));
+  wrap_err!(clash.activate(&profiles, false))
 }
 
-/// delete profile item
+/// get the log level of the running core
 #[tauri::command]
-pub fn delete_profile(
-  index: String,
-  clash_state: State<'_, ClashState>,
-  profiles_state: State<'_, ProfilesState>,
-) -> Result<(), String> {
-  let mut profiles = profiles_state.0.lock().unwrap();
-
-  if wrap_err!(profiles.delete_item(index))? {
+pub async fn get_clash_log_level(clash_state: State<'_, ClashState>) -> Result<String, String> {
+  let info = {
     let clash = clash_state.0.lock().unwrap();
-    wrap_err!(clash.activate(&profiles, false))?;
-  }
+    clash.info.clone()
+  };
 
-  Ok(())
-}
+  let configs = wrap_err!(clash_api::get_configs(&info).await)?;
 
-/// patch the profile config
-#[tauri::command]
-pub fn patch_profile(
-  index: String,
-  profile: PrfItem,
-  profiles_state: State<'_, ProfilesState>,
-) -> Result<(), String> {
-  let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(profiles.patch_item(index, profile))
+  match configs["log-level"].as_str() {
+    Some(level) => Ok(level.into()),
+    None => ret_err!("failed to get the log level of the core"),
+  }
 }
 
-/// run vscode command to edit the profile
+/// get the config currently loaded in the core
+/// including the chain enhancements and the live patches
 #[tauri::command]
-pub fn view_profile(index: String, profiles_state: State<'_, ProfilesState>) -> Result<(), String> {
-  let profiles = profiles_state.0.lock().unwrap();
-  let item = wrap_err!(profiles.get_item(&index))?;
-
-  let file = item.file.clone();
-  if file.is_none() {
-    ret_err!("the file is null");
-  }
-
-  let path = dirs::app_profiles_dir().join(file.unwrap());
-  if !path.exists() {
-    ret_err!("the file not found");
-  }
+pub async fn get_runtime_config(
+  with_file: Option<bool>,
+  clash_state: State<'_, ClashState>,
+) -> Result<RuntimeConfig, String> {
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
 
-  // use vscode first
-  if let Ok(code) = which::which("code") {
-    #[cfg(target_os = "windows")]
-    {
-      use std::os::windows::process::CommandExt;
-
-      if let Err(err) = Command::new(code)
-        .creation_flags(0x08000000)
-        .arg(path)
-        .spawn()
-      {
-        log::error!("{err}");
-        return Err("failed to open file by VScode".into());
-      }
-    }
+  let general = wrap_err!(clash_api::get_configs(&info).await)?;
+  let rules = wrap_err!(clash_api::get_rules(&info).await)?;
 
-    #[cfg(not(target_os = "windows"))]
-    if let Err(err) = Command::new(code).arg(path).spawn() {
-      log::error!("{err}");
-      return Err("failed to open file by VScode".into());
+  let file = match with_file.unwrap_or(false) {
+    true => {
+      let path = dirs::profiles_temp_path();
+      Some(config::read_yaml::<Mapping>(path))
     }
+    false => None,
+  };
 
-    return Ok(());
-  }
-
-  open_path_cmd(path, "failed to open file by `open`")
+  Ok(RuntimeConfig {
+    general,
+    rules,
+    file,
+  })
 }
 
-/// restart the sidecar
+/// change the log level of the running core
+/// and keep it in `config.yaml` for the next start
+/// nothing is saved if the core rejects it
 #[tauri::command]
-pub fn restart_sidecar(
+pub async fn set_clash_log_level(
+  level: String,
   clash_state: State<'_, ClashState>,
-  profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
+  wrap_err!(Clash::validate_log_level(&level))?;
+
+  let info = {
+    let clash = clash_state.0.lock().unwrap();
+    clash.info.clone()
+  };
+
+  wrap_err!(clash_api::patch_log_level(&info, &level).await)?;
+
   let mut clash = clash_state.0.lock().unwrap();
-  let mut profiles = profiles_state.0.lock().unwrap();
+  wrap_err!(clash.set_log_level(&level))
+}
+
+/// forward the clash logs by the `verge://log` event
+/// only the logs above the `level` are sent
+/// the running stream would be replaced
+#[tauri::command]
+pub fn start_log_stream(
+  level: Option<String>,
+  app_handle: tauri::AppHandle,
+  log_stream_state: State<'_, LogStreamState>,
+) -> Result<(), String> {
+  let (sender, receiver) = oneshot::channel();
+
+  // drop the old sender to stop the old stream
+  *log_stream_state.0.lock().unwrap() = Some(sender);
 
-  wrap_err!(clash.restart_sidecar(&mut profiles))
+  resolve::resolve_logs(app_handle, level, receiver);
+  Ok(())
 }
 
-/// get the clash core info from the state
-/// the caller can also get the infomation by clash's api
+/// stop the log stream when the logs are not needed
 #[tauri::command]
-pub fn get_clash_info(clash_state: State<'_, ClashState>) -> Result<ClashInfo, String> {
-  let clash = clash_state.0.lock().unwrap();
-  Ok(clash.info.clone())
+pub fn stop_log_stream(log_stream_state: State<'_, LogStreamState>) -> Result<(), String> {
+  log_stream_state.0.lock().unwrap().take();
+  Ok(())
 }
 
-/// update the clash core config
-/// after putting the change to the clash core
-/// then we should save the latest config
+/// check whether the auto launch points to the current exe
 #[tauri::command]
-pub fn patch_clash_config(
-  payload: Mapping,
-  clash_state: State<'_, ClashState>,
-  verge_state: State<'_, VergeState>,
-  profiles_state: State<'_, ProfilesState>,
-) -> Result<(), String> {
-  let mut clash = clash_state.0.lock().unwrap();
-  let mut verge = verge_state.0.lock().unwrap();
-  let mut profiles = profiles_state.0.lock().unwrap();
-  wrap_err!(clash.patch_config(payload, &mut verge, &mut profiles))
+pub fn get_launch_status(verge_state: State<'_, VergeState>) -> Result<LaunchStatus, String> {
+  let verge = verge_state.0.lock().unwrap();
+  Ok(verge.launch_status())
 }
 
 /// get the system proxy
+/// `tampered` if it is not the one set by the app
 #[tauri::command]
-pub fn get_sys_proxy() -> Result<SysProxyConfig, String> {
-  wrap_err!(SysProxyConfig::get_sys())
+pub fn get_sys_proxy(verge_state: State<'_, VergeState>) -> Result<SysProxyStatus, String> {
+  let sys = wrap_err!(SysProxyConfig::get_sys())?;
+
+  // only the enabled one could be overridden
+  let verge = verge_state.0.lock().unwrap();
+  let tampered = match verge.cur_sysproxy.as_ref() {
+    Some(cur) if cur.enable => !cur.is_same(&sys),
+    _ => false,
+  };
+
+  Ok(SysProxyStatus { sys, tampered })
 }
 
 /// get the current proxy config
@@ -261,6 +1412,15 @@ pub fn get_cur_proxy(verge_state: State<'_, VergeState>) -> Result<Option<SysPro
   Ok(verge.cur_sysproxy.clone())
 }
 
+/// get the effective system proxy bypass
+/// which is merged by the os default and the user's
+#[tauri::command]
+pub fn get_sys_proxy_bypass(verge_state: State<'_, VergeState>) -> Result<String, String> {
+  let verge = verge_state.0.lock().unwrap();
+  let bypass = verge.config.system_proxy_bypass.clone();
+  Ok(SysProxyConfig::merge_bypass(bypass))
+}
+
 /// get the verge config
 #[tauri::command]
 pub fn get_verge_config(verge_state: State<'_, VergeState>) -> Result<VergeConfig, String> {
@@ -279,34 +1439,235 @@ pub fn get_verge_config(verge_state: State<'_, VergeState>) -> Result<VergeConfi
 #[tauri::command]
 pub fn patch_verge_config(
   payload: VergeConfig,
+  app_handle: tauri::AppHandle,
   clash_state: State<'_, ClashState>,
   verge_state: State<'_, VergeState>,
   profiles_state: State<'_, ProfilesState>,
 ) -> Result<(), String> {
   let tun_mode = payload.enable_tun_mode.clone();
+  let core = payload.core.clone();
+  let hotkeys = payload.hotkeys.clone();
+  let theme_changed = payload.theme_mode.is_some();
+  let proxy_changed = payload.enable_system_proxy.is_some()
+    || payload.enable_tun_mode.is_some()
+    || payload.sysproxy_with_tun.is_some();
+
+  // check the core before saving it
+  if let Some(core) = core.as_ref() {
+    wrap_err!(Clash::check_core(core))?;
+  }
 
   // change tun mode
-  if tun_mode.is_some() {
+  // skip the unchanged one carried by the stale payload
+  let old_tun_mode = {
+    let verge = verge_state.0.lock().unwrap();
+    verge.config.enable_tun_mode.unwrap_or(false)
+  };
+
+  if let Some(tun_mode) = tun_mode.filter(|t| *t != old_tun_mode) {
     let mut clash = clash_state.0.lock().unwrap();
     let profiles = profiles_state.0.lock().unwrap();
 
-    wrap_err!(clash.tun_mode(tun_mode.unwrap()))?;
+    wrap_err!(clash.tun_mode(tun_mode))?;
     clash.update_config();
     wrap_err!(clash.activate(&profiles, false))?;
   }
 
+  {
+    let mut verge = verge_state.0.lock().unwrap();
+
+    // register the new hotkeys before saving
+    // restore the old ones if failed
+    if let Some(hotkeys) = hotkeys.as_ref() {
+      if let Err(err) = hotkey::register(&app_handle, hotkeys) {
+        let old_hotkeys = verge.config.hotkeys.clone().unwrap_or_default();
+        log_if_err!(hotkey::register(&app_handle, &old_hotkeys));
+        ret_err!(err.to_string());
+      }
+    }
+
+    wrap_err!(verge.patch_config(payload))?;
+
+    // tint the window effect by the new theme
+    if let (true, Some(window)) = (theme_changed, app_handle.get_window("main")) {
+      window::apply_effect(&window, &verge.config);
+    }
+  }
+
+  // change the clash core
+  if core.is_some() {
+    let mut clash = clash_state.0.lock().unwrap();
+    let mut verge = verge_state.0.lock().unwrap();
+    let mut profiles = profiles_state.0.lock().unwrap();
+
+    wrap_err!(clash.restart_sidecar(&mut profiles))?;
+    wrap_err!(verge.update_port(clash.info.port.clone()))?;
+  }
+
+  // switch the tray icon by the system proxy and the theme
+  if proxy_changed || theme_changed {
+    let clash = clash_state.0.lock().unwrap();
+    let verge = verge_state.0.lock().unwrap();
+    tray::update_icon(&app_handle, clash.sidecar.is_some(), Some(&verge));
+  }
+
+  Ok(())
+}
+
+/// backup all the config to the zip file
+#[tauri::command]
+pub fn backup_config(path: String) -> Result<(), String> {
+  wrap_err!(backup::create_backup(PathBuf::from(path)))
+}
+
+/// restore the config from the zip file
+/// then reload all the states and reactivate
+#[tauri::command]
+pub fn restore_config(
+  path: String,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  wrap_err!(backup::restore_backup(PathBuf::from(path)))?;
+
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  *profiles = Profiles::read_file();
+  log_if_err!(profiles.ensure_current());
+  wrap_err!(clash.restart_sidecar(&mut profiles))?;
+  wrap_err!(verge.reload_config(clash.info.port.clone()))?;
+
+  let hotkeys = verge.config.hotkeys.clone().unwrap_or_default();
+  log_if_err!(hotkey::register(&app_handle, &hotkeys));
+  tray::update_menu(&app_handle, &profiles);
+
+  if let Some(window) = app_handle.get_window("main") {
+    log_if_err!(window.emit("verge://refresh-verge-config", "yes"));
+  }
+
+  Ok(())
+}
+
+/// reset the verge config to the defaults and clear the chain
+/// the profiles are removed too unless `keep_profiles`
+/// the `confirm` must be true to avoid the accidental reset
+#[tauri::command]
+pub fn reset_all_config(
+  confirm: bool,
+  keep_profiles: Option<bool>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  if !confirm {
+    ret_err!("the reset should be confirmed");
+  }
+
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  // restore the original system proxy before the config is gone
+  verge.reset_sysproxy();
+  log::info!("reset the system proxy");
+
+  if verge.config.enable_tun_mode.unwrap_or(false) {
+    wrap_err!(clash.tun_mode(false))?;
+    log::info!("disable the tun mode");
+  }
+
+  wrap_err!(VergeConfig::default().save_file())?;
+  log::info!("reset the verge config");
+
+  wrap_err!(profiles.put_chain(None))?;
+  wrap_err!(profiles.save_file())?;
+  log::info!("clear the profile chain");
+
+  wrap_err!(RuleOverrides::default().clear())?;
+  log::info!("clear the rule overrides");
+
+  if !keep_profiles.unwrap_or(false) {
+    let uids = profiles
+      .get_items()
+      .iter()
+      .filter_map(|item| item.uid.clone())
+      .collect::<Vec<_>>();
+
+    for uid in uids.into_iter() {
+      wrap_err!(profiles.delete_item(uid))?;
+    }
+    log::info!("remove all the profiles");
+  }
+
+  wrap_err!(clash.restart_sidecar(&mut profiles))?;
+  wrap_err!(verge.reload_config(clash.info.port.clone()))?;
+
+  let hotkeys = verge.config.hotkeys.clone().unwrap_or_default();
+  log_if_err!(hotkey::register(&app_handle, &hotkeys));
+  tray::update_menu(&app_handle, &profiles);
+  tray::update_icon(&app_handle, clash.sidecar.is_some(), Some(&verge));
+
+  if let Some(window) = app_handle.get_window("main") {
+    log_if_err!(window.emit("verge://refresh-verge-config", "yes"));
+  }
+
+  Ok(())
+}
+
+/// turn on/off the system proxy for a while
+/// the config is only changed if `persist`
+#[tauri::command]
+pub fn toggle_system_proxy(
+  enable: bool,
+  persist: Option<bool>,
+  app_handle: tauri::AppHandle,
+  clash_state: State<'_, ClashState>,
+  verge_state: State<'_, VergeState>,
+  profiles_state: State<'_, ProfilesState>,
+) -> Result<(), String> {
+  if persist.unwrap_or(false) {
+    let payload = VergeConfig {
+      enable_system_proxy: Some(enable),
+      ..VergeConfig::default()
+    };
+    return patch_verge_config(
+      payload,
+      app_handle,
+      clash_state,
+      verge_state,
+      profiles_state,
+    );
+  }
+
+  let clash = clash_state.0.lock().unwrap();
   let mut verge = verge_state.0.lock().unwrap();
-  wrap_err!(verge.patch_config(payload))?;
+  wrap_err!(verge.toggle_sysproxy(enable))?;
 
+  tray::update_icon(&app_handle, clash.sidecar.is_some(), Some(&verge));
   Ok(())
 }
 
 /// kill all sidecars when update app
 #[tauri::command]
-pub fn kill_sidecars() {
+pub fn kill_sidecars(app_handle: tauri::AppHandle) {
+  window::save_main_window_state(&app_handle);
   api::process::kill_children();
 }
 
+/// get the disk usage of the profiles and logs dir
+#[tauri::command]
+pub fn get_storage_info() -> Result<dirs::StorageInfo, String> {
+  Ok(dirs::StorageInfo {
+    profiles: dirs::dir_usage(&dirs::app_profiles_dir()),
+    logs: dirs::dir_usage(&dirs::app_logs_dir()),
+  })
+}
+
 /// open app config dir
 #[tauri::command]
 pub fn open_app_dir() -> Result<(), String> {
@@ -314,6 +1675,25 @@ pub fn open_app_dir() -> Result<(), String> {
   open_path_cmd(app_dir, "failed to open app dir")
 }
 
+/// open the template of the new local profile
+/// create it from the built-in one if not exists
+#[tauri::command]
+pub fn open_profile_template() -> Result<(), String> {
+  let path = dirs::profile_template_path();
+
+  if !path.exists() {
+    wrap_err!(fs::write(&path, tmpl::ITEM_LOCAL))?;
+  }
+
+  open_path_cmd(path, "failed to open the profile template")
+}
+
+/// reset the template of the new local profile to the built-in one
+#[tauri::command]
+pub fn reset_profile_template() -> Result<(), String> {
+  wrap_err!(fs::write(dirs::profile_template_path(), tmpl::ITEM_LOCAL))
+}
+
 /// open logs dir
 #[tauri::command]
 pub fn open_logs_dir() -> Result<(), String> {
@@ -345,6 +1725,44 @@ fn open_path_cmd(path: PathBuf, err_str: &str) -> Result<(), String> {
     result = Command::new("xdg-open").arg(&path).spawn();
   }
 
+  wait_open_cmd(result, &path, err_str)
+}
+
+/// open the file manager with the file selected
+/// only open the parent dir on linux
+fn reveal_path_cmd(path: PathBuf, err_str: &str) -> Result<(), String> {
+  let result;
+
+  #[cfg(target_os = "windows")]
+  {
+    use std::os::windows::process::CommandExt;
+
+    result = Command::new("explorer")
+      .creation_flags(0x08000000)
+      .arg(format!("/select,{}", path.display()))
+      .spawn();
+  }
+
+  #[cfg(target_os = "macos")]
+  {
+    result = Command::new("open").arg("-R").arg(&path).spawn();
+  }
+
+  #[cfg(target_os = "linux")]
+  {
+    let dir = path.parent().unwrap_or(&path);
+    result = Command::new("xdg-open").arg(dir).spawn();
+  }
+
+  wait_open_cmd(result, &path, err_str)
+}
+
+/// wait for the open command to exit
+fn wait_open_cmd(
+  result: std::io::Result<std::process::Child>,
+  path: &Path,
+  err_str: &str,
+) -> Result<(), String> {
   match result {
     Ok(child) => match child.wait_with_output() {
       Ok(out) => {
@@ -352,7 +1770,7 @@ fn open_path_cmd(path: PathBuf, err_str: &str) -> Result<(), String> {
         // 因此仅做warn log且不返回错误
         if let Some(code) = out.status.code() {
           if code != 0 {
-            log::warn!("failed to open {:?} (code {})", &path, code);
+            log::warn!("failed to open {:?} (code {})", path, code);
             log::warn!(
               "open cmd stdout: {}, stderr: {}",
               String::from_utf8_lossy(&out.stdout),
@@ -362,12 +1780,12 @@ fn open_path_cmd(path: PathBuf, err_str: &str) -> Result<(), String> {
         }
       }
       Err(err) => {
-        log::error!("failed to open {:?} for {err}", &path);
+        log::error!("failed to open {:?} for {err}", path);
         return Err(err_str.into());
       }
     },
     Err(err) => {
-      log::error!("failed to open {:?} for {err}", &path);
+      log::error!("failed to open {:?} for {err}", path);
       return Err(err_str.into());
     }
   }
@@ -1,14 +1,66 @@
-use super::{PrfEnhancedResult, Profiles, Verge};
-use crate::utils::{config, dirs, help};
-use anyhow::{bail, Result};
+use super::{
+  clash_api, PrfEnhancedResult, PrfSelected, Profiles, RuleOverrides, Verge, VergeConfig,
+};
+use crate::{
+  log_if_err,
+  states::{ClashState, ProfilesState},
+  utils::{config, dirs, help, tray},
+};
+use anyhow::{anyhow, bail, Result};
+use once_cell::sync::Lazy;
 use reqwest::header::HeaderMap;
 use serde::{Deserialize, Serialize};
 use serde_yaml::{Mapping, Value};
-use std::{collections::HashMap, time::Duration};
+use std::{
+  collections::HashMap,
+  sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc::{self, Receiver},
+    Mutex,
+  },
+  time::{Duration, Instant},
+};
 use tauri::api::process::{Command, CommandChild, CommandEvent};
-use tauri::Window;
+use tauri::{utils::platform::current_exe, Manager, Window};
 use tokio::time::sleep;
 
+/// the cores which could be used as the sidecar
+const CLASH_CORES: [&str; 2] = ["clash", "clash-meta"];
+
+/// the core exits in this time if the config is invalid
+const CORE_STARTUP_TIME: Duration = Duration::from_secs(10);
+
+/// the max time to wait for the startup error by the activation
+const CORE_STARTUP_WAIT: Duration = Duration::from_millis(1500);
+
+/// the core is ready when the api is listening
+const CORE_READY_LOG: &str = "RESTful API listening";
+
+/// the ports listened by the clash core
+const PORT_KEYS: [&str; 5] = [
+  "port",
+  "socks-port",
+  "mixed-port",
+  "redir-port",
+  "tproxy-port",
+];
+
+/// the keys of the outbound interface binding
+/// removed from the config if set to null or empty
+const INTERFACE_KEYS: [&str; 2] = ["interface-name", "routing-mark"];
+
+/// the log levels supported by the clash core
+const LOG_LEVELS: [&str; 5] = ["debug", "info", "warning", "error", "silent"];
+
+/// the max retry count of restarting the crashed sidecar
+const MAX_RESTART_COUNT: usize = 5;
+
+static RESTART_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// the free ports picked for the occupied ones at launch
+/// only used by the runtime config, never saved to `config.yaml`
+static PICKED_PORTS: Lazy<Mutex<Mapping>> = Lazy::new(|| Mutex::new(Mapping::new()));
+
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct ClashInfo {
   /// clash sidecar status
@@ -36,18 +88,28 @@ pub struct Clash {
 
   /// save the main window
   pub window: Option<Window>,
+
+  /// receive the error if the sidecar fails on startup
+  /// taken by the first check so that the others never wait
+  startup: Mutex<Option<Receiver<String>>>,
+
+  /// the error of the launch on setup
+  /// kept until the window takes it
+  pub launch_error: Option<String>,
 }
 
 impl Clash {
   pub fn new() -> Clash {
     let config = Clash::read_config();
-    let info = Clash::get_info(&config);
+    let info = Clash::get_info(&Clash::runtime_ports(config.clone()));
 
     Clash {
       config,
       info,
       sidecar: None,
       window: None,
+      startup: Mutex::new(None),
+      launch_error: None,
     }
   }
 
@@ -56,6 +118,14 @@ impl Clash {
     config::read_yaml::<Mapping>(dirs::clash_path())
   }
 
+  /// replace the ports with the picked ones
+  fn runtime_ports(mut config: Mapping) -> Mapping {
+    for (key, value) in PICKED_PORTS.lock().unwrap().iter() {
+      config.insert(key.clone(), value.clone());
+    }
+    config
+  }
+
   /// save the clash config
   fn save_config(&self) -> Result<()> {
     config::save_yaml(
@@ -129,27 +199,343 @@ impl Clash {
     }
   }
 
+  /// read the clash port from `config.yaml` directly
+  /// which is the same as the running core's unless picked
+  pub fn read_port() -> Option<String> {
+    Clash::get_info(&Clash::runtime_ports(Clash::read_config())).port
+  }
+
+  /// read the port which supports socks from `config.yaml` directly
+  pub fn read_socks_port() -> Option<String> {
+    let config = Clash::runtime_ports(Clash::read_config());
+
+    ["socks-port", "mixed-port"]
+      .iter()
+      .find_map(|key| match config.get(&Value::from(*key)) {
+        Some(Value::String(port)) => Some(port.clone()),
+        Some(Value::Number(port)) => Some(port.to_string()),
+        _ => None,
+      })
+  }
+
+  /// read the last used clash mode
+  /// fallback to the mode in `config.yaml`
+  pub fn read_mode() -> String {
+    if let Some(mode) = VergeConfig::new().clash_mode {
+      return mode;
+    }
+
+    let config = Clash::read_config();
+    match config.get(&Value::from("mode")) {
+      Some(Value::String(mode)) => mode.clone(),
+      _ => "rule".into(),
+    }
+  }
+
+  /// get all the ports listened by the core
+  fn get_ports(&self) -> Vec<(&'static str, u16)> {
+    Clash::collect_ports(&self.config)
+  }
+
+  /// collect the ports and the `external-controller` port of the config
+  fn collect_ports(config: &Mapping) -> Vec<(&'static str, u16)> {
+    let mut ports = vec![];
+
+    for key in PORT_KEYS {
+      let port = match config.get(&Value::from(key)) {
+        Some(Value::Number(port)) => port.as_u64(),
+        Some(Value::String(port)) => port.parse::<u64>().ok(),
+        _ => None,
+      };
+
+      if let Some(port) = port.filter(|p| *p > 0 && *p <= u16::MAX as u64) {
+        ports.push((key, port as u16));
+      }
+    }
+
+    let server_port = Clash::get_info(config).server.and_then(|server| {
+      let (_, port) = server.rsplit_once(':')?;
+      port.parse::<u16>().ok()
+    });
+
+    if let Some(port) = server_port {
+      ports.push(("external-controller", port));
+    }
+
+    ports
+  }
+
+  /// check the ports of the patched config
+  /// `0` means disabled, except the `mixed-port`
+  /// the ports should not conflict with each other
+  fn validate_ports(config: &Mapping) -> Result<()> {
+    for key in PORT_KEYS {
+      let value = match config.get(&Value::from(key)) {
+        Some(value) => value,
+        None => continue,
+      };
+
+      let port = match value {
+        Value::Number(port) => port.as_u64(),
+        Value::String(port) => port.parse::<u64>().ok(),
+        _ => None,
+      };
+
+      match port {
+        Some(0) if key != "mixed-port" => {}
+        Some(port) if port > 0 && port <= u16::MAX as u64 => {}
+        _ => bail!("invalid {key} {value:?}, should be in 1-65535"),
+      }
+    }
+
+    let ports = Clash::collect_ports(config);
+
+    for (index, (key, port)) in ports.iter().enumerate() {
+      if let Some((other, _)) = ports[index + 1..].iter().find(|(_, p)| p == port) {
+        bail!("the {key} {port} conflicts with the {other}");
+      }
+    }
+
+    Ok(())
+  }
+
+  /// the `interface-name` should exist
+  /// and the `routing-mark` should be a 32-bit number
+  /// the `names` are the interfaces of the os
+  fn validate_interface(patch: &Mapping, names: &[String]) -> Result<()> {
+    match patch.get(&Value::from("interface-name")) {
+      None | Some(Value::Null) => {}
+      Some(Value::String(name)) if name.is_empty() => {}
+      Some(Value::String(name)) => {
+        if !names.contains(name) {
+          bail!("the interface \"{name}\" does not exist");
+        }
+      }
+      Some(value) => bail!("invalid interface-name {value:?}"),
+    }
+
+    match patch.get(&Value::from("routing-mark")) {
+      None | Some(Value::Null) => {}
+      Some(Value::String(mark)) if mark.is_empty() => {}
+      Some(Value::Number(mark)) if mark.as_u64().map_or(false, |m| m <= u32::MAX as u64) => {}
+      Some(value) => bail!("invalid routing-mark {value:?}, should be a 32-bit number"),
+    }
+
+    Ok(())
+  }
+
+  /// check whether the ports are occupied by other processes
+  /// never wait here since the caller holds the lock
+  fn check_ports(&self) -> Result<()> {
+    let occupied = self
+      .get_ports()
+      .into_iter()
+      .filter(|(_, port)| !help::port_available(*port))
+      .collect::<Vec<_>>();
+
+    if occupied.is_empty() {
+      return Ok(());
+    }
+
+    let occupied = occupied
+      .iter()
+      .map(|(key, port)| format!("{key} {port}"))
+      .collect::<Vec<String>>()
+      .join(", ");
+
+    bail!("the ports are occupied by other processes: {occupied}");
+  }
+
+  /// replace the occupied ports with the free ones for this launch
+  /// the system proxy follows `info.port` by the caller
+  fn pick_ports(&mut self) -> Result<()> {
+    let ports = self.get_ports();
+    let mut used = ports.iter().map(|(_, port)| *port).collect::<Vec<u16>>();
+    let mut picked = Mapping::new();
+
+    for (key, port) in ports.into_iter() {
+      if help::port_available(port) {
+        continue;
+      }
+
+      let new_port = match (port.saturating_add(1)..port.saturating_add(100))
+        .find(|p| !used.contains(p) && help::port_available(*p))
+      {
+        Some(new_port) => new_port,
+        None => bail!("failed to find a free port for {key}"),
+      };
+
+      log::info!("change the {key} from {port} to {new_port}");
+      used.push(new_port);
+
+      let value = match key {
+        "external-controller" => {
+          let server = self.config.get(&Value::from(key)).and_then(|v| v.as_str());
+          let host = server
+            .and_then(|s| s.rsplit_once(':'))
+            .map(|(host, _)| host);
+          Value::from(format!("{}:{new_port}", host.unwrap_or("127.0.0.1")))
+        }
+        _ => Value::from(new_port as u64),
+      };
+
+      picked.insert(Value::from(key), value);
+    }
+
+    *PICKED_PORTS.lock().unwrap() = picked;
+    self.info = Clash::get_info(&Clash::runtime_ports(self.config.clone()));
+
+    if let Some(window) = self.window.as_ref() {
+      log_if_err!(window.emit("verge://refresh-clash-config", "yes"));
+    }
+
+    Ok(())
+  }
+
   /// save the main window
   pub fn set_window(&mut self, win: Option<Window>) {
     self.window = win;
   }
 
+  /// check whether the core could be used as the sidecar
+  pub fn check_core(core: &str) -> Result<()> {
+    if !CLASH_CORES.contains(&core) {
+      bail!("invalid clash core \"{core}\"");
+    }
+
+    // the sidecar binary is next to the app
+    let app_exe = current_exe()?;
+    let bin_dir = match app_exe.parent() {
+      Some(dir) => dir.to_path_buf(),
+      None => bail!("failed to get the app dir"),
+    };
+
+    #[cfg(target_os = "windows")]
+    let bin_name = format!("{core}.exe");
+    #[cfg(not(target_os = "windows"))]
+    let bin_name = core.to_string();
+
+    if !bin_dir.join(bin_name).exists() {
+      bail!("the binary of the clash core \"{core}\" is missing");
+    }
+
+    Ok(())
+  }
+
   /// run clash sidecar
+  /// use the core selected in `verge.yaml`
   pub fn run_sidecar(&mut self) -> Result<()> {
     let app_dir = dirs::app_home_dir();
     let app_dir = app_dir.as_os_str().to_str().unwrap();
 
-    match Command::new_sidecar("clash") {
-      Ok(cmd) => match cmd.args(["-d", app_dir]).spawn() {
+    let verge = VergeConfig::new();
+    let core = verge.core.unwrap_or("clash".into());
+    Clash::check_core(&core)?;
+
+    // try the ports of `config.yaml` again
+    PICKED_PORTS.lock().unwrap().clear();
+    self.info = Clash::get_info(&self.config);
+
+    if let Err(err) = self.check_ports() {
+      if !verge.auto_pick_ports.unwrap_or(false) {
+        return Err(err);
+      }
+
+      log::warn!("{err}, try to pick the free ports");
+      self.pick_ports()?;
+    }
+
+    // the other picked ports are applied by the runtime config
+    // but the core only listens on the controller of the launch
+    let mut args = vec!["-d".to_string(), app_dir.to_string()];
+    let controller = PICKED_PORTS
+      .lock()
+      .unwrap()
+      .get(&Value::from("external-controller"))
+      .cloned();
+    if let Some(Value::String(server)) = controller {
+      args.push("-ext-ctl".into());
+      args.push(server);
+    }
+
+    // reject the traffic before the profile is activated
+    // otherwise the rules of `config.yaml` send them direct
+    if verge.enable_kill_switch.unwrap_or(false) {
+      let path = dirs::startup_temp_path();
+      let config = Clash::kill_switch(Clash::runtime_ports(self.config.clone()));
+      config::save_yaml(path.clone(), &config, Some("# Clash Verge Startup File"))?;
+
+      args.push("-f".into());
+      args.push(path.as_os_str().to_str().unwrap().to_string());
+    }
+
+    match Command::new_sidecar(core) {
+      Ok(cmd) => match cmd.args(args).spawn() {
         Ok((mut rx, cmd_child)) => {
+          let pid = cmd_child.pid();
+          let window = self.window.clone();
+          let start = Instant::now();
+          let (startup_tx, startup_rx) = mpsc::channel();
+
           self.sidecar = Some(cmd_child);
+          *self.startup.lock().unwrap() = Some(startup_rx);
+
+          if let Some(window) = self.window.as_ref() {
+            tray::update_icon(&window.app_handle(), true, None);
+          }
 
           // clash log
           tauri::async_runtime::spawn(async move {
+            // the reason why the core exits on startup
+            let mut last_error = None;
+            let mut startup_tx = Some(startup_tx);
+
             while let Some(event) = rx.recv().await {
+              if start.elapsed() >= CORE_STARTUP_TIME {
+                startup_tx = None;
+              }
+
               match event {
-                CommandEvent::Stdout(line) => log::info!("[clash]: {}", line),
-                CommandEvent::Stderr(err) => log::error!("[clash]: {}", err),
+                CommandEvent::Stdout(line) => {
+                  log::info!("[clash]: {}", line);
+                  last_error = Clash::parse_core_error(&line).or(last_error);
+
+                  if line.contains(CORE_READY_LOG) {
+                    startup_tx = None;
+                  }
+                }
+                CommandEvent::Stderr(err) => {
+                  log::error!("[clash]: {}", err);
+                  last_error = Clash::parse_core_error(&err).or(last_error);
+
+                  if let Some(tx) = startup_tx.take() {
+                    let _ = tx.send(last_error.clone().unwrap_or(err));
+                  }
+                }
+                CommandEvent::Terminated(payload) => {
+                  log::warn!("[clash]: terminated with code {:?}", payload.code);
+
+                  if start.elapsed() < CORE_STARTUP_TIME {
+                    if let Some(err) = last_error.take() {
+                      let err = format!("the clash core exited for \"{err}\"");
+
+                      if let Some(tx) = startup_tx.take() {
+                        let _ = tx.send(err.clone());
+                      }
+                      Clash::notice_error(window.as_ref(), err);
+                    }
+                  }
+
+                  if let Some(tx) = startup_tx.take() {
+                    let _ = tx.send(format!(
+                      "the clash core exited with code {:?}",
+                      payload.code
+                    ));
+                  }
+
+                  Clash::notify_terminated(window.as_ref(), pid);
+                  Clash::recover_sidecar(window.clone(), pid, start.elapsed()).await;
+                }
                 _ => {}
               }
             }
@@ -162,8 +548,98 @@ impl Clash {
     }
   }
 
+  /// get the message of the error or fatal log line
+  /// like `level=fatal msg="Parse config error: ..."`
+  fn parse_core_error(line: &str) -> Option<String> {
+    if !line.contains("level=error") && !line.contains("level=fatal") {
+      return None;
+    }
+
+    let msg = line.split_once("msg=")?.1.trim();
+    let msg = match msg.strip_prefix('"') {
+      Some(msg) => msg.rsplit_once('"').map_or(msg, |(msg, _)| msg),
+      None => msg,
+    };
+
+    Some(msg.replace("\\\"", "\""))
+  }
+
+  /// show the error of the core to the user
+  fn notice_error(window: Option<&Window>, err: String) {
+    log::error!("{err}");
+
+    if let Some(window) = window {
+      log_if_err!(window.emit("verge://notice-error", err));
+    }
+  }
+
+  /// gray the tray icon if the running core exits
+  /// the dropped or restarted one is ignored
+  fn notify_terminated(window: Option<&Window>, pid: u32) {
+    if let Some(window) = window {
+      let app_handle = window.app_handle();
+      let clash_state = app_handle.state::<ClashState>();
+      let is_current = {
+        let clash = clash_state.0.lock().unwrap();
+        clash.sidecar.as_ref().map(|c| c.pid()) == Some(pid)
+      };
+
+      if is_current {
+        tray::update_icon(&app_handle, false, None);
+      }
+    }
+  }
+
+  /// rerun the sidecar if it exits unexpectedly
+  /// enabled by the `auto_restart_core`
+  async fn recover_sidecar(window: Option<Window>, pid: u32, uptime: Duration) {
+    if !VergeConfig::new().auto_restart_core.unwrap_or(false) || window.is_none() {
+      return;
+    }
+
+    let app_handle = window.unwrap().app_handle();
+
+    // the sidecar has been dropped or restarted manually
+    let is_crashed = |clash: &Clash| clash.sidecar.as_ref().map(|c| c.pid()) == Some(pid);
+
+    if !is_crashed(&app_handle.state::<ClashState>().0.lock().unwrap()) {
+      return;
+    }
+
+    // reset the count if the core has been running for a while
+    if uptime > Duration::from_secs(60) {
+      RESTART_COUNT.store(0, Ordering::SeqCst);
+    }
+
+    let count = RESTART_COUNT.fetch_add(1, Ordering::SeqCst);
+    if count >= MAX_RESTART_COUNT {
+      log::error!("the clash core crashed too many times, stop restarting");
+      return;
+    }
+
+    // backoff 1s 2s 4s ...
+    sleep(Duration::from_secs(1 << count)).await;
+
+    let clash_state = app_handle.state::<ClashState>();
+    let profiles_state = app_handle.state::<ProfilesState>();
+    let mut clash = clash_state.0.lock().unwrap();
+    let profiles = profiles_state.0.lock().unwrap();
+
+    if !is_crashed(&clash) {
+      return;
+    }
+
+    log::info!("restart the clash core (retry {})", count + 1);
+
+    clash.sidecar = None;
+    log_if_err!(clash.run_sidecar());
+    log_if_err!(clash.activate(&profiles, false));
+  }
+
   /// drop clash sidecar
   pub fn drop_sidecar(&mut self) -> Result<()> {
+    *self.startup.lock().unwrap() = None;
+
     if let Some(sidecar) = self.sidecar.take() {
       sidecar.kill()?;
     }
@@ -174,15 +650,62 @@ impl Clash {
   /// should reactivate profile after restart
   pub fn restart_sidecar(&mut self, profiles: &mut Profiles) -> Result<()> {
     self.update_config();
+
+    let killed = self.sidecar.is_some();
     self.drop_sidecar()?;
+
+    if killed && self.check_ports().is_err() && self.window.is_some() {
+      self.rerun_sidecar();
+      return Ok(());
+    }
+
     self.run_sidecar()?;
     self.activate(profiles, false)
   }
 
+  /// the killed core may hold the ports for a moment
+  /// wait for them to be released without the lock
+  /// then run the sidecar and reactivate the profile
+  fn rerun_sidecar(&self) {
+    let window = self.window.clone().unwrap();
+    let ports = self.get_ports();
+
+    tauri::async_runtime::spawn(async move {
+      for _ in 0..5 {
+        sleep(Duration::from_millis(200)).await;
+
+        if ports.iter().all(|(_, port)| help::port_available(*port)) {
+          break;
+        }
+      }
+
+      let app_handle = window.app_handle();
+      let clash_state = app_handle.state::<ClashState>();
+      let profiles_state = app_handle.state::<ProfilesState>();
+      let mut clash = clash_state.0.lock().unwrap();
+      let profiles = profiles_state.0.lock().unwrap();
+
+      // the sidecar has been run by the others
+      if clash.sidecar.is_some() {
+        return;
+      }
+
+      let result = clash.run_sidecar();
+      let result = result.and_then(|_| clash.activate(&profiles, false));
+
+      if let Err(err) = result {
+        Clash::notice_error(
+          Some(&window),
+          format!("failed to restart the clash core, {err}"),
+        );
+      }
+    });
+  }
+
   /// update the clash info
   pub fn update_config(&mut self) {
     self.config = Clash::read_config();
-    self.info = Clash::get_info(&self.config);
+    self.info = Clash::get_info(&Clash::runtime_ports(self.config.clone()));
   }
 
   /// patch update the clash config
@@ -192,19 +715,63 @@ impl Clash {
     verge: &mut Verge,
     profiles: &mut Profiles,
   ) -> Result<()> {
-    let mix_port_key = Value::from("mixed-port");
-    let mut port = None;
+    let mode_key = Value::from("mode");
+    let port_keys = PORT_KEYS.map(Value::from);
+    let controller_keys = [Value::from("secret"), Value::from("external-controller")];
+    let interface_keys = INTERFACE_KEYS.map(Value::from);
+    let mut mode = None;
+    let mut port_changed = false;
+    let mut controller_changed = false;
+    let mut interface_changed = false;
+
+    if patch.contains_key(&mode_key) && verge.config.enable_kill_switch.unwrap_or(false) {
+      bail!("the mode could not be changed while the kill switch is on");
+    }
+
+    // nothing would be saved if the ports conflict
+    let mut config = self.config.clone();
+    for (key, value) in patch.iter() {
+      config.insert(key.clone(), value.clone());
+    }
+    Clash::validate_ports(&config)?;
+
+    // only list the interfaces if it is changed
+    let names = match patch.contains_key(&Value::from("interface-name")) {
+      true => help::interface_names()?,
+      false => vec![],
+    };
+    Clash::validate_interface(&patch, &names)?;
 
     for (key, value) in patch.into_iter() {
       let value = value.clone();
 
-      // check whether the mix_port is changed
-      if key == mix_port_key {
-        if value.is_number() {
-          port = value.as_i64().as_ref().map(|n| n.to_string());
-        } else {
-          port = value.as_str().as_ref().map(|s| s.to_string());
-        }
+      // check whether the ports are changed
+      if port_keys.contains(&key) {
+        port_changed |= self.config.get(&key) != Some(&value);
+      }
+
+      if key == mode_key {
+        mode = value.as_str().map(|s| s.to_string());
+      }
+
+      if controller_keys.contains(&key) {
+        controller_changed |= self.config.get(&key) != Some(&value);
+      }
+
+      // clear the binding by null or empty
+      if interface_keys.contains(&key) {
+        let value = match value {
+          Value::Null => None,
+          Value::String(s) if s.is_empty() => None,
+          value => Some(value),
+        };
+
+        interface_changed |= self.config.get(&key) != value.as_ref();
+        match value {
+          Some(value) => self.config.insert(key.clone(), value),
+          None => self.config.remove(&key),
+        };
+        continue;
       }
 
       self.config.insert(key.clone(), value);
@@ -212,14 +779,55 @@ impl Clash {
 
     self.save_config()?;
 
-    if let Some(port) = port {
+    // remember the mode for the other profiles
+    if mode.is_some() {
+      verge.patch_config(VergeConfig {
+        clash_mode: mode,
+        ..VergeConfig::default()
+      })?;
+    }
+
+    if port_changed {
+      // the system proxy follows the new mixed port
+      self.restart_sidecar(profiles)?;
+
+      let config = Clash::runtime_ports(self.config.clone());
+      let port = match config.get(&Value::from("mixed-port")) {
+        Some(Value::Number(port)) => Some(port.to_string()),
+        Some(Value::String(port)) => Some(port.clone()),
+        _ => self.info.port.clone(),
+      };
+      verge.init_sysproxy(port);
+    } else if controller_changed {
+      // the running core could not change the secret by api
       self.restart_sidecar(profiles)?;
-      verge.init_sysproxy(Some(port));
+    } else if interface_changed {
+      // regenerate the runtime config and reload the core
+      self.activate(profiles, false)?;
     }
 
     Ok(())
   }
 
+  /// check the log level supported by the core
+  pub fn validate_log_level(level: &str) -> Result<()> {
+    if !LOG_LEVELS.contains(&level) {
+      bail!("invalid log level \"{level}\"");
+    }
+    Ok(())
+  }
+
+  /// save the log level of the core to `config.yaml`
+  /// it is applied to the running core by api
+  pub fn set_log_level(&mut self, level: &str) -> Result<()> {
+    Clash::validate_log_level(level)?;
+
+    self
+      .config
+      .insert(Value::from("log-level"), Value::from(level));
+    self.save_config()
+  }
+
   /// enable tun mode
   /// only revise the config and restart the
   pub fn tun_mode(&mut self, enable: bool) -> Result<()> {
@@ -299,10 +907,35 @@ impl Clash {
     self.save_config()
   }
 
+  /// apply the kill switch or the rule overrides to the enhanced config
+  /// same as the config to run by the `activate`
+  pub fn runtime_config(config: Mapping, verge: &VergeConfig) -> Mapping {
+    match verge.enable_kill_switch.unwrap_or(false) {
+      true => Clash::kill_switch(config),
+      false => RuleOverrides::new().apply(config),
+    }
+  }
+
   /// activate the profile
   /// generate a new profile to the temp_dir
   /// then put the path to the clash core
-  fn _activate(info: ClashInfo, config: Mapping, window: Option<Window>) -> Result<()> {
+  /// and restore the selected proxies of the profile
+  /// the rule overrides are applied on the top of all
+  /// unless the kill switch is on
+  fn _activate(
+    info: ClashInfo,
+    config: Mapping,
+    current: Option<String>,
+    selected: Vec<PrfSelected>,
+    window: Option<Window>,
+  ) -> Result<()> {
+    let verge = VergeConfig::new();
+    let kill_switch = verge.enable_kill_switch.unwrap_or(false);
+    let config = Clash::runtime_config(config, &verge);
+
+    // the mode is fixed to `rule` by the kill switch
+    let clash_mode = verge.clash_mode.filter(|_| !kill_switch);
+
     let temp_path = dirs::profiles_temp_path();
     config::save_yaml(temp_path.clone(), &config, Some("# Clash Verge Temp File"))?;
 
@@ -330,7 +963,32 @@ impl Clash {
             match builder.send().await {
               Ok(resp) => {
                 if resp.status() != 204 {
-                  log::error!("failed to activate clash for status \"{}\"", resp.status());
+                  // the core replies the reason why the config is rejected
+                  let status = resp.status();
+                  let message = resp.json::<serde_json::Value>().await.ok();
+                  let message = message.as_ref().and_then(|m| m["message"].as_str());
+
+                  let err = match message {
+                    Some(message) => format!("failed to activate the profile for \"{message}\""),
+                    None => format!("failed to activate clash for status \"{status}\""),
+                  };
+                  Clash::notice_error(window.as_ref(), err);
+                } else {
+                  if let Some(mode) = clash_mode.as_ref() {
+                    if let Err(err) = clash_api::patch_mode(&info, mode).await {
+                      log::error!("failed to restore the clash mode for {err}");
+                    }
+                  }
+                  // the established connections are not affected by the new rules
+                  if kill_switch {
+                    if let Err(err) = clash_api::close_connections(&info, None).await {
+                      log::error!("failed to close the connections for {err}");
+                    }
+                  }
+                  match clash_api::apply_selected(&info, &selected).await {
+                    Ok(selected) => Clash::record_selected(window.as_ref(), current, selected),
+                    Err(err) => log::error!("failed to restore the selected proxies for {err}"),
+                  }
                 }
 
                 // emit the window to update something
@@ -353,9 +1011,48 @@ impl Clash {
     Ok(())
   }
 
+  /// reject all the traffic in the rule mode
+  /// the proxies are kept to restore the selections
+  fn kill_switch(mut config: Mapping) -> Mapping {
+    let rules = vec![Value::from("MATCH,REJECT")];
+
+    config.insert(Value::from("mode"), Value::from("rule"));
+    config.insert(Value::from("rules"), Value::Sequence(rules));
+    config
+  }
+
+  /// save the selections of the core to the activated profile
+  /// so that the profiles never share the same group memory
+  fn record_selected(window: Option<&Window>, uid: Option<String>, selected: Vec<PrfSelected>) {
+    if let (Some(window), Some(uid)) = (window, uid) {
+      let app_handle = window.app_handle();
+      let profiles_state = app_handle.state::<ProfilesState>();
+      let mut profiles = profiles_state.0.lock().unwrap();
+
+      log_if_err!(profiles.set_selected(&uid, selected));
+    }
+  }
+
   /// enhanced profiles mode
   /// only change the enhanced profiles
   pub fn activate_enhanced(&self, profiles: &Profiles, delay: bool) -> Result<()> {
+    let info = self.info.clone();
+    let current = profiles.get_current();
+    let selected = profiles.get_selected();
+    let window = self.window.clone();
+
+    self.gen_enhanced_config(profiles, delay, move |config| {
+      log_if_err!(Self::_activate(info, config, current, selected, window));
+    })
+  }
+
+  /// run the enhanced profiles chain by the window
+  /// the callback would receive the final config
+  /// without applying it to the clash core
+  pub fn gen_enhanced_config<F>(&self, profiles: &Profiles, delay: bool, callback: F) -> Result<()>
+  where
+    F: FnOnce(Mapping) + Send + 'static,
+  {
     if self.window.is_none() {
       bail!("failed to get the main window");
     }
@@ -364,12 +1061,10 @@ impl Clash {
     let event_name = help::get_uid("e");
     let event_name = format!("enhanced-cb-{event_name}");
 
-    let info = self.info.clone();
-    let mut config = self.config.clone();
+    let mut config = Clash::runtime_ports(self.config.clone());
 
     // generate the payload
     let payload = profiles.gen_enhanced(event_name.clone())?;
-    let window = self.window.clone();
 
     win.once(&event_name, move |event| {
       if let Some(result) = event.payload() {
@@ -404,7 +1099,7 @@ impl Clash {
 
           log::info!("profile enhanced status {}", result.status);
 
-          Self::_activate(info, config, window).unwrap();
+          callback(config);
         }
 
         if let Some(error) = result.error {
@@ -424,9 +1119,60 @@ impl Clash {
     Ok(())
   }
 
+  /// run the script with the current profile by the window
+  /// only for testing, nothing would be applied
+  pub fn test_script<F>(&self, profiles: &Profiles, script: String, callback: F) -> Result<()>
+  where
+    F: FnOnce(Result<Mapping>) + Send + 'static,
+  {
+    if self.window.is_none() {
+      bail!("failed to get the main window");
+    }
+
+    let win = self.window.clone().unwrap();
+    let event_name = help::get_uid("t");
+    let event_name = format!("script-test-cb-{event_name}");
+
+    let payload = profiles.gen_script_test(script, event_name.clone())?;
+
+    win.once(&event_name, move |event| {
+      let result = event
+        .payload()
+        .map(serde_json::from_str::<PrfEnhancedResult>);
+
+      let result = match result {
+        Some(Ok(PrfEnhancedResult {
+          error: Some(error), ..
+        })) => Err(anyhow!(error)),
+        Some(Ok(PrfEnhancedResult {
+          data: Some(data), ..
+        })) => Ok(data),
+        _ => Err(anyhow!("failed to get the script result")),
+      };
+
+      callback(result);
+    });
+
+    win.emit("script-test-handler", payload)?;
+    Ok(())
+  }
+
+  /// wait a moment for the error of the sidecar on startup
+  /// nothing if it is ready or has been checked
+  /// the later errors are noticed by the log task
+  fn startup_error(&self) -> Option<String> {
+    let startup = self.startup.lock().unwrap().take()?;
+    startup.recv_timeout(CORE_STARTUP_WAIT).ok()
+  }
+
   /// activate the profile
   /// auto activate enhanced profile
+  /// fail if the sidecar exits on startup
   pub fn activate(&self, profiles: &Profiles, delay: bool) -> Result<()> {
+    if let Some(err) = self.startup_error() {
+      bail!(err);
+    }
+
     let gen_map = profiles.gen_activate()?;
     let info = self.info.clone();
     let mut config = self.config.clone();
@@ -434,8 +1180,11 @@ impl Clash {
     for (key, value) in gen_map.into_iter() {
       config.insert(key, value);
     }
+    let config = Clash::runtime_ports(config);
 
-    Self::_activate(info, config, self.window.clone())?;
+    let current = profiles.get_current();
+    let selected = profiles.get_selected();
+    Self::_activate(info, config, current, selected, self.window.clone())?;
     self.activate_enhanced(profiles, delay)
   }
 }
@@ -453,3 +1202,66 @@ impl Drop for Clash {
     }
   }
 }
+
+#[test]
+fn test_validate_ports() {
+  let config = |yaml: &str| serde_yaml::from_str::<Mapping>(yaml).unwrap();
+
+  let valid = config("mixed-port: 7890\nsocks-port: 0\nexternal-controller: 127.0.0.1:9090\n");
+  let invalid = config("mixed-port: 70000\n");
+  let disabled = config("mixed-port: 0\n");
+  let conflict = config("mixed-port: 7890\nport: 7890\n");
+  let controller = config("mixed-port: 9090\nexternal-controller: 127.0.0.1:9090\n");
+
+  assert!(Clash::validate_ports(&valid).is_ok());
+  assert!(Clash::validate_ports(&invalid).is_err());
+  assert!(Clash::validate_ports(&disabled).is_err());
+  assert!(Clash::validate_ports(&conflict).is_err());
+  assert!(Clash::validate_ports(&controller).is_err());
+}
+
+#[test]
+fn test_parse_core_error() {
+  let fatal = r#"time="2022-03-01T10:00:00+08:00" level=fatal msg="Parse config error: rules[23] [DOMAIN,a.com,Foo] error: proxy [Foo] not found""#;
+  let info = r#"time="2022-03-01T10:00:00+08:00" level=info msg="Start initial compatible provider default""#;
+
+  assert_eq!(
+    Clash::parse_core_error(fatal),
+    Some("Parse config error: rules[23] [DOMAIN,a.com,Foo] error: proxy [Foo] not found".into())
+  );
+  assert_eq!(Clash::parse_core_error(info), None);
+  assert_eq!(
+    Clash::parse_core_error("level=error msg=bind failed"),
+    Some("bind failed".into())
+  );
+}
+
+#[test]
+fn test_validate_interface() {
+  let names = vec!["eth0".to_string(), "wlan0".to_string()];
+  let check = |key: &str, value: Value| {
+    let patch = Mapping::from_iter([(Value::from(key), value)]);
+    Clash::validate_interface(&patch, &names)
+  };
+
+  assert!(check("routing-mark", Value::from(6666)).is_ok());
+  assert!(check("routing-mark", Value::from(-1)).is_err());
+  assert!(check("routing-mark", Value::from("abc")).is_err());
+  assert!(check("interface-name", Value::from("")).is_ok());
+  assert!(check("interface-name", Value::Null).is_ok());
+  assert!(check("interface-name", Value::from("eth0")).is_ok());
+  assert!(check("interface-name", Value::from("eth1")).is_err());
+}
+
+#[test]
+fn test_kill_switch() {
+  let config = "mode: global\nproxies: []\nrules:\n  - DOMAIN,a.com,DIRECT\n  - MATCH,Proxy\n";
+  let config = Clash::kill_switch(serde_yaml::from_str::<Mapping>(config).unwrap());
+
+  assert_eq!(config.get(&Value::from("mode")), Some(&Value::from("rule")));
+  assert_eq!(
+    config.get(&Value::from("rules")),
+    Some(&Value::Sequence(vec![Value::from("MATCH,REJECT")]))
+  );
+  assert!(config.contains_key(&Value::from("proxies")));
+}
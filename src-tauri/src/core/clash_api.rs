@@ -0,0 +1,735 @@
+//! Some requests to the clash external controller
+
+use super::{ClashInfo, PrfSelected};
+use anyhow::{bail, Context, Result};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use reqwest::{header::HeaderMap, Method, RequestBuilder, StatusCode};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, sync::Arc, time::Duration};
+use tokio::sync::Semaphore;
+
+/// the default url and timeout(ms) to test the delay
+pub const DELAY_TEST_URL: &str = "http://www.gstatic.com/generate_204";
+pub const DELAY_TEST_TIMEOUT: u64 = 5000;
+
+/// the max count of the delay tests at the same time
+const MAX_DELAY_TASKS: usize = 16;
+
+/// the timeout of the connectivity check
+const CONNECTIVITY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// the dead core should not block the status check for long
+const PING_TIMEOUT: Duration = Duration::from_millis(800);
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ClashVersion {
+  pub version: String,
+
+  /// clash premium core
+  #[serde(default)]
+  pub premium: bool,
+
+  /// clash meta core
+  #[serde(default)]
+  pub meta: bool,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct CoreStatus {
+  /// the sidecar has been spawned
+  pub running: bool,
+
+  /// the clash core port
+  pub port: Option<String>,
+
+  /// the external controller responds
+  pub responsive: bool,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct Connectivity {
+  /// the http status code
+  pub status: Option<u16>,
+
+  /// the elapsed time in ms
+  pub delay: Option<u64>,
+
+  /// the reason if the request failed
+  pub error: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ConnectivityResult {
+  /// through the clash port
+  pub proxy: Connectivity,
+
+  /// without the proxy for comparison
+  pub direct: Connectivity,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct ProviderInfo {
+  pub name: String,
+
+  /// `proxy` or `rule`
+  pub kind: String,
+
+  /// `HTTP` or `File`
+  pub vehicle_type: String,
+
+  /// the last updated time reported by the core
+  pub updated_at: Option<String>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct DnsAnswer {
+  pub name: String,
+
+  /// the record type code, `1` for A and `28` for AAAA
+  #[serde(rename = "type")]
+  pub record_type: u16,
+
+  #[serde(rename = "TTL")]
+  pub ttl: u32,
+
+  pub data: String,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct Traffic {
+  pub up: u64,
+
+  pub down: u64,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct LogItem {
+  #[serde(rename = "type")]
+  pub log_type: String,
+
+  pub payload: String,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct RuleItem {
+  #[serde(rename = "type")]
+  pub rule_type: String,
+
+  pub payload: String,
+
+  pub proxy: String,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct RuntimeConfig {
+  /// the general config reported by the core
+  pub general: serde_json::Value,
+
+  /// the rules loaded by the core
+  pub rules: Vec<RuleItem>,
+
+  /// the generated config file loaded by the core
+  pub file: Option<serde_yaml::Mapping>,
+}
+
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct TrafficTotal {
+  #[serde(rename = "uploadTotal")]
+  pub upload_total: u64,
+
+  #[serde(rename = "downloadTotal")]
+  pub download_total: u64,
+}
+
+/// build the request to the clash api
+/// with the `external-controller` and `secret` in the info
+pub fn request(info: &ClashInfo, method: Method, path: &str) -> Result<RequestBuilder> {
+  build_request(info, method, path, Some(Duration::from_secs(5)))
+}
+
+/// the streaming api should not timeout
+fn build_request(
+  info: &ClashInfo,
+  method: Method,
+  path: &str,
+  timeout: Option<Duration>,
+) -> Result<RequestBuilder> {
+  let server = match info.server.as_ref() {
+    Some(server) => server,
+    None => bail!("failed to get the clash external controller"),
+  };
+  let url = format!("http://{server}{path}");
+
+  let mut headers = HeaderMap::new();
+  headers.insert("Content-Type", "application/json".parse().unwrap());
+
+  if let Some(secret) = info.secret.as_ref().filter(|s| !s.is_empty()) {
+    let secret = format!("Bearer {secret}").parse()?;
+    headers.insert("Authorization", secret);
+  }
+
+  let mut builder = reqwest::ClientBuilder::new().no_proxy();
+
+  if let Some(timeout) = timeout {
+    builder = builder.timeout(timeout);
+  }
+
+  let client = builder.build()?;
+
+  Ok(client.request(method, url).headers(headers))
+}
+
+/// GET /version
+pub async fn get_version(info: &ClashInfo) -> Result<ClashVersion> {
+  let resp = request(info, Method::GET, "/version")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  let mut version = resp.json::<ClashVersion>().await?;
+
+  // some meta cores only mark it in the version string
+  if version.version.to_lowercase().contains("meta") {
+    version.meta = true;
+  }
+
+  Ok(version)
+}
+
+/// GET /version with a short timeout
+/// only check whether the core responds
+pub async fn ping(info: &ClashInfo) -> bool {
+  match build_request(info, Method::GET, "/version", Some(PING_TIMEOUT)) {
+    Ok(request) => match request.send().await {
+      Ok(resp) => resp.status().is_success(),
+      Err(_) => false,
+    },
+    Err(_) => false,
+  }
+}
+
+/// GET /traffic
+/// the core pushes a json line per second
+/// keep calling the callback until the connection is closed
+pub async fn stream_traffic<F: FnMut(Traffic)>(info: &ClashInfo, callback: F) -> Result<()> {
+  stream_json(info, "/traffic", callback).await
+}
+
+/// GET /logs?level=xxx
+/// the core pushes a json line per log
+/// keep calling the callback until the connection is closed
+pub async fn stream_logs<F: FnMut(LogItem)>(
+  info: &ClashInfo,
+  level: Option<&str>,
+  callback: F,
+) -> Result<()> {
+  let path = match level {
+    Some(level) => format!(
+      "/logs?level={}",
+      utf8_percent_encode(level, NON_ALPHANUMERIC)
+    ),
+    None => "/logs".into(),
+  };
+
+  stream_json(info, &path, callback).await
+}
+
+/// read the json lines of the streaming api
+async fn stream_json<T: DeserializeOwned, F: FnMut(T)>(
+  info: &ClashInfo,
+  path: &str,
+  mut callback: F,
+) -> Result<()> {
+  let mut resp = build_request(info, Method::GET, path, None)?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  let mut buffer: Vec<u8> = vec![];
+
+  while let Some(chunk) = resp.chunk().await? {
+    buffer.extend_from_slice(&chunk);
+
+    while let Some(pos) = buffer.iter().position(|b| *b == b'\n') {
+      let line: Vec<u8> = buffer.drain(..=pos).collect();
+
+      match serde_json::from_slice::<T>(&line) {
+        Ok(data) => callback(data),
+        Err(err) => log::debug!("failed to parse the stream of \"{path}\" for {err}"),
+      }
+    }
+  }
+
+  Ok(())
+}
+
+/// GET /connections
+/// only the total traffic
+pub async fn get_traffic_total(info: &ClashInfo) -> Result<TrafficTotal> {
+  let resp = request(info, Method::GET, "/connections")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  Ok(resp.json::<TrafficTotal>().await?)
+}
+
+/// GET /connections
+/// return the raw json
+pub async fn get_connections(info: &ClashInfo) -> Result<serde_json::Value> {
+  let resp = request(info, Method::GET, "/connections")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  Ok(resp.json::<serde_json::Value>().await?)
+}
+
+/// DELETE /connections/:id
+/// close all the connections if the id is none
+pub async fn close_connections(info: &ClashInfo, id: Option<&str>) -> Result<()> {
+  let path = match id {
+    Some(id) => format!("/connections/{id}"),
+    None => "/connections".into(),
+  };
+
+  let resp = request(info, Method::DELETE, &path)?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!(
+      "failed to close the connections for status \"{}\"",
+      resp.status()
+    );
+  }
+
+  Ok(())
+}
+
+/// GET /proxies
+pub async fn get_proxies(info: &ClashInfo) -> Result<serde_json::Value> {
+  let resp = request(info, Method::GET, "/proxies")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  Ok(resp.json::<serde_json::Value>().await?)
+}
+
+/// PUT /proxies/:group
+/// change the selected proxy of the `Selector` group
+pub async fn select_proxy(info: &ClashInfo, group: &str, name: &str) -> Result<()> {
+  let group = utf8_percent_encode(group, NON_ALPHANUMERIC);
+  let mut data = HashMap::new();
+  data.insert("name", name);
+
+  let resp = request(info, Method::PUT, &format!("/proxies/{group}"))?
+    .json(&data)
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!(
+      "failed to select the proxy for status \"{}\"",
+      resp.status()
+    );
+  }
+
+  Ok(())
+}
+
+/// GET /providers/proxies and /providers/rules
+/// the rule providers are only supported by the premium or meta core
+/// the `Compatible` ones are generated by the core and skipped
+pub async fn get_providers(info: &ClashInfo) -> Result<Vec<ProviderInfo>> {
+  let mut providers = vec![];
+
+  for (kind, path) in [
+    ("proxy", "/providers/proxies"),
+    ("rule", "/providers/rules"),
+  ] {
+    let resp = request(info, Method::GET, path)?
+      .send()
+      .await
+      .context("failed to connect to the clash core")?;
+
+    if !resp.status().is_success() {
+      log::debug!("failed to get {path} for status \"{}\"", resp.status());
+      continue;
+    }
+
+    let json = resp.json::<serde_json::Value>().await?;
+
+    if let Some(map) = json["providers"].as_object() {
+      for (name, item) in map.iter() {
+        let vehicle_type = item["vehicleType"].as_str().unwrap_or_default();
+
+        if vehicle_type == "Compatible" {
+          continue;
+        }
+
+        providers.push(ProviderInfo {
+          name: name.clone(),
+          kind: kind.into(),
+          vehicle_type: vehicle_type.into(),
+          updated_at: item["updatedAt"].as_str().map(|t| t.to_string()),
+        });
+      }
+    }
+  }
+
+  Ok(providers)
+}
+
+/// PUT /providers/proxies/:name or /providers/rules/:name
+/// refresh the provider by the core
+pub async fn update_provider(info: &ClashInfo, provider: &ProviderInfo) -> Result<()> {
+  let name = utf8_percent_encode(&provider.name, NON_ALPHANUMERIC);
+  let path = match provider.kind.as_str() {
+    "rule" => format!("/providers/rules/{name}"),
+    _ => format!("/providers/proxies/{name}"),
+  };
+
+  // the provider may be large
+  let resp = build_request(info, Method::PUT, &path, Some(Duration::from_secs(30)))?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!(
+      "failed to update the provider \"{}\" for status \"{}\"",
+      provider.name,
+      resp.status()
+    );
+  }
+
+  Ok(())
+}
+
+/// PUT /configs?force=true
+/// load the config file and return the error message of the core
+pub async fn put_configs(info: &ClashInfo, path: &Path) -> Result<()> {
+  let mut data = HashMap::new();
+  data.insert("path", path.to_string_lossy().to_string());
+
+  let resp = request(info, Method::PUT, "/configs?force=true")?
+    .json(&data)
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    let status = resp.status();
+    let message = resp
+      .json::<serde_json::Value>()
+      .await
+      .ok()
+      .and_then(|body| body["message"].as_str().map(|m| m.to_string()));
+
+    match message {
+      Some(message) => bail!("{message}"),
+      None => bail!("failed to load the config for status \"{status}\""),
+    }
+  }
+
+  Ok(())
+}
+
+/// GET /configs
+pub async fn get_configs(info: &ClashInfo) -> Result<serde_json::Value> {
+  let resp = request(info, Method::GET, "/configs")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!("failed to get the configs for status \"{}\"", resp.status());
+  }
+
+  Ok(resp.json::<serde_json::Value>().await?)
+}
+
+/// GET /rules
+pub async fn get_rules(info: &ClashInfo) -> Result<Vec<RuleItem>> {
+  let resp = request(info, Method::GET, "/rules")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!("failed to get the rules for status \"{}\"", resp.status());
+  }
+
+  let json = resp.json::<serde_json::Value>().await?;
+
+  match json.get("rules") {
+    Some(rules) => Ok(serde_json::from_value(rules.clone())?),
+    None => Ok(vec![]),
+  }
+}
+
+/// PATCH /configs
+/// only change the log level of the running core
+pub async fn patch_log_level(info: &ClashInfo, level: &str) -> Result<()> {
+  let mut data = HashMap::new();
+  data.insert("log-level", level);
+
+  let resp = request(info, Method::PATCH, "/configs")?
+    .json(&data)
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!(
+      "failed to patch the log level for status \"{}\"",
+      resp.status()
+    );
+  }
+
+  Ok(())
+}
+
+/// PATCH /configs
+/// only change the mode of the running core
+pub async fn patch_mode(info: &ClashInfo, mode: &str) -> Result<()> {
+  let mut data = HashMap::new();
+  data.insert("mode", mode);
+
+  let resp = request(info, Method::PATCH, "/configs")?
+    .json(&data)
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!("failed to patch the mode for status \"{}\"", resp.status());
+  }
+
+  Ok(())
+}
+
+/// DELETE /cache/fakeip/flush
+/// not supported by the old cores
+pub async fn flush_fakeip(info: &ClashInfo) -> Result<()> {
+  let resp = request(info, Method::DELETE, "/cache/fakeip/flush")?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!(
+      "failed to flush the fakeip cache for status \"{}\"",
+      resp.status()
+    );
+  }
+
+  Ok(())
+}
+
+/// GET /dns/query?name=:name&type=:type
+/// only supported by the meta core
+/// the fakeip core answers with the mapped fake ip
+pub async fn query_dns(info: &ClashInfo, name: &str, record_type: &str) -> Result<Vec<DnsAnswer>> {
+  let name = utf8_percent_encode(name, NON_ALPHANUMERIC);
+  let record_type = utf8_percent_encode(record_type, NON_ALPHANUMERIC);
+  let path = format!("/dns/query?name={name}&type={record_type}");
+
+  let resp = request(info, Method::GET, &path)?
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if resp.status() == StatusCode::NOT_FOUND {
+    bail!("the dns query is unsupported by the core");
+  }
+
+  if !resp.status().is_success() {
+    bail!("failed to query the dns for status \"{}\"", resp.status());
+  }
+
+  let json = resp.json::<serde_json::Value>().await?;
+
+  match json.get("Answer") {
+    Some(answer) => Ok(serde_json::from_value(answer.clone())?),
+    None => Ok(vec![]),
+  }
+}
+
+/// restore the selected proxies
+/// skip the group or the proxy which does not exist
+/// return the selections of all the `Selector` groups
+pub async fn apply_selected(
+  info: &ClashInfo,
+  selected: &Vec<PrfSelected>,
+) -> Result<Vec<PrfSelected>> {
+  let proxies = get_proxies(info).await?;
+
+  let groups = match proxies["proxies"].as_object() {
+    Some(groups) => groups,
+    None => return Ok(vec![]),
+  };
+
+  let selected = selected
+    .iter()
+    .filter_map(|each| Some((each.name.as_ref()?, each.now.as_ref()?)))
+    .collect::<HashMap<&String, &String>>();
+
+  let mut result = vec![];
+
+  for (group, item) in groups.iter() {
+    if item["type"].as_str() != Some("Selector") {
+      continue;
+    }
+
+    let mut now = item["now"].as_str().map(|n| n.to_string());
+
+    if let Some(&name) = selected.get(group) {
+      let exists = match item["all"].as_array() {
+        Some(all) => all.iter().any(|p| p.as_str() == Some(name)),
+        None => false,
+      };
+
+      if exists && now.as_ref() != Some(name) {
+        match select_proxy(info, group, name).await {
+          Ok(_) => now = Some(name.clone()),
+          Err(err) => log::error!("{err}"),
+        }
+      }
+    }
+
+    result.push(PrfSelected {
+      name: Some(group.clone()),
+      now,
+    });
+  }
+
+  Ok(result)
+}
+
+/// GET /proxies/:name/delay
+/// return the delay in ms
+pub async fn get_proxy_delay(info: &ClashInfo, name: &str, url: &str, timeout: u64) -> Result<u64> {
+  let name = utf8_percent_encode(name, NON_ALPHANUMERIC);
+  let url = utf8_percent_encode(url, NON_ALPHANUMERIC);
+  let path = format!("/proxies/{name}/delay?timeout={timeout}&url={url}");
+
+  let resp = request(info, Method::GET, &path)?
+    .timeout(Duration::from_millis(timeout + 1000))
+    .send()
+    .await
+    .context("failed to connect to the clash core")?;
+
+  if !resp.status().is_success() {
+    bail!("failed to test the delay for status \"{}\"", resp.status());
+  }
+
+  let data = resp.json::<serde_json::Value>().await?;
+
+  match data["delay"].as_u64() {
+    Some(delay) => Ok(delay),
+    None => bail!("failed to get the delay"),
+  }
+}
+
+/// test the delay of all the proxies in the group
+/// the failed one would be `None`
+pub async fn get_group_delay(
+  info: &ClashInfo,
+  group: &str,
+  url: &str,
+  timeout: u64,
+) -> Result<HashMap<String, Option<u64>>> {
+  let proxies = get_proxies(info).await?;
+
+  let names = match proxies["proxies"][group]["all"].as_array() {
+    Some(all) => all
+      .iter()
+      .filter_map(|p| p.as_str().map(|p| p.to_string()))
+      .collect::<Vec<String>>(),
+    None => bail!("failed to find the group \"{group}\""),
+  };
+
+  // one dead proxy should not stall the others
+  let semaphore = Arc::new(Semaphore::new(MAX_DELAY_TASKS));
+
+  let handles = names
+    .into_iter()
+    .map(|name| {
+      let info = info.clone();
+      let url = url.to_string();
+      let semaphore = semaphore.clone();
+
+      tauri::async_runtime::spawn(async move {
+        let _permit = semaphore.acquire().await;
+        let delay = get_proxy_delay(&info, &name, &url, timeout).await.ok();
+        (name, delay)
+      })
+    })
+    .collect::<Vec<_>>();
+
+  let mut result = HashMap::new();
+
+  for handle in handles {
+    if let Ok((name, delay)) = handle.await {
+      result.insert(name, delay);
+    }
+  }
+
+  Ok(result)
+}
+
+/// request the url through the clash port
+/// or directly if the port is none
+pub async fn check_connectivity(url: &str, port: Option<&str>) -> Connectivity {
+  let mut builder = reqwest::ClientBuilder::new()
+    .no_proxy()
+    .timeout(CONNECTIVITY_TIMEOUT);
+
+  if let Some(port) = port {
+    match reqwest::Proxy::all(format!("http://127.0.0.1:{port}")) {
+      Ok(proxy) => builder = builder.proxy(proxy),
+      Err(err) => {
+        return Connectivity {
+          error: Some(err.to_string()),
+          ..Connectivity::default()
+        }
+      }
+    }
+  }
+
+  let client = match builder.build() {
+    Ok(client) => client,
+    Err(err) => {
+      return Connectivity {
+        error: Some(err.to_string()),
+        ..Connectivity::default()
+      }
+    }
+  };
+
+  let start = std::time::Instant::now();
+
+  match client.get(url).send().await {
+    Ok(resp) => Connectivity {
+      status: Some(resp.status().as_u16()),
+      delay: Some(start.elapsed().as_millis() as u64),
+      error: None,
+    },
+    Err(err) if err.is_timeout() => Connectivity {
+      error: Some(format!(
+        "timed out after {}s",
+        CONNECTIVITY_TIMEOUT.as_secs()
+      )),
+      ..Connectivity::default()
+    },
+    Err(err) => Connectivity {
+      error: Some(err.to_string()),
+      ..Connectivity::default()
+    },
+  }
+}
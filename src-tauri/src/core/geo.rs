@@ -0,0 +1,163 @@
+use crate::utils::{dirs, http};
+use anyhow::{bail, Result};
+use reqwest::Client;
+use sha2::{Digest, Sha256};
+use std::{fs, time::Duration};
+
+/// the databases smaller than it must be broken
+const GEO_DATA_MIN_SIZE: usize = 1024;
+
+/// the timeout of downloading one database
+const GEO_DATA_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// the metadata marker of the maxmind database
+const MMDB_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// the geo databases used by the core and their default sources
+/// the meta core also uses the `dat` ones
+pub fn geo_data_files(core: Option<&str>) -> Vec<(&'static str, &'static str)> {
+  let mut files = vec![(
+    "Country.mmdb",
+    "https://github.com/Dreamacro/maxmind-geoip/releases/latest/download/Country.mmdb",
+  )];
+
+  if core == Some("clash-meta") {
+    files.push((
+      "geoip.dat",
+      "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geoip.dat",
+    ));
+    files.push((
+      "geosite.dat",
+      "https://github.com/Loyalsoldier/v2ray-rules-dat/releases/latest/download/geosite.dat",
+    ));
+  }
+
+  files
+}
+
+/// download the geo databases to the clash data dir
+/// the `{file}` of the source is replaced with the file name
+/// nothing is replaced unless all of them are verified
+pub async fn download_geo_data(
+  source: Option<String>,
+  core: Option<String>,
+) -> Result<Vec<String>> {
+  let client = http::client_builder(true)?
+    .timeout(GEO_DATA_TIMEOUT)
+    .build()?;
+
+  let mut list = vec![];
+
+  for (name, default_url) in geo_data_files(core.as_deref()) {
+    let url = match source.as_ref() {
+      Some(source) if !source.trim().is_empty() => source.trim().replace("{file}", name),
+      _ => default_url.to_string(),
+    };
+
+    match download(&client, &url).await {
+      Ok(data) => match verify_geo_data(name, &data, fetch_checksum(&client, &url).await) {
+        Ok(_) => list.push((name, data)),
+        Err(err) => bail!("failed to verify \"{name}\", {err}"),
+      },
+      Err(err) => bail!("failed to download \"{name}\", {err}"),
+    };
+  }
+
+  let home = dirs::app_home_dir();
+  let mut updated = vec![];
+
+  for (name, data) in list {
+    let temp = home.join(format!("{name}.download"));
+
+    fs::write(&temp, data)?;
+    fs::rename(&temp, home.join(name))?;
+    log::info!("updated the geo data \"{name}\"");
+    updated.push(name.to_string());
+  }
+
+  Ok(updated)
+}
+
+async fn download(client: &Client, url: &str) -> Result<Vec<u8>> {
+  let resp = client.get(url).send().await?;
+  let status = resp.status();
+
+  if !status.is_success() {
+    bail!("the response status is \"{status}\"");
+  }
+
+  let length = resp.content_length();
+  let data = resp.bytes().await?.to_vec();
+
+  if let Some(length) = length {
+    if data.len() as u64 != length {
+      bail!("got {} bytes, expected {length} bytes", data.len());
+    }
+  }
+
+  Ok(data)
+}
+
+/// the sources may provide the `.sha256sum` file
+/// skip the checksum if not
+async fn fetch_checksum(client: &Client, url: &str) -> Option<String> {
+  let resp = client.get(format!("{url}.sha256sum")).send().await.ok()?;
+
+  if !resp.status().is_success() {
+    return None;
+  }
+
+  let text = resp.text().await.ok()?;
+  let checksum = text.split_whitespace().next()?;
+
+  match checksum.len() == 64 && checksum.chars().all(|c| c.is_ascii_hexdigit()) {
+    true => Some(checksum.to_lowercase()),
+    false => None,
+  }
+}
+
+/// check the size, the format and the checksum of the database
+fn verify_geo_data(name: &str, data: &[u8], checksum: Option<String>) -> Result<()> {
+  if data.len() < GEO_DATA_MIN_SIZE {
+    bail!("the size {} is too small", data.len());
+  }
+
+  // maxmind database has the metadata marker
+  // the dat ones are the protobuf list starting with the first field
+  if name.ends_with(".mmdb") && !data.windows(MMDB_MARKER.len()).any(|w| w == MMDB_MARKER) {
+    bail!("invalid mmdb format");
+  }
+  if name.ends_with(".dat") && data[0] != 0x0a {
+    bail!("invalid dat format");
+  }
+
+  if let Some(checksum) = checksum {
+    let digest = format!("{:x}", Sha256::digest(data));
+
+    if digest != checksum {
+      bail!("the checksum \"{digest}\" does not match \"{checksum}\"");
+    }
+  }
+
+  Ok(())
+}
+
+#[test]
+fn test_verify_geo_data() {
+  let mut mmdb = vec![0u8; GEO_DATA_MIN_SIZE];
+  mmdb.extend_from_slice(MMDB_MARKER);
+
+  assert!(verify_geo_data("Country.mmdb", &mmdb, None).is_ok());
+  assert!(verify_geo_data("Country.mmdb", &mmdb[..GEO_DATA_MIN_SIZE], None).is_err());
+  assert!(verify_geo_data("Country.mmdb", &[0u8; 10], None).is_err());
+
+  let checksum = format!("{:x}", Sha256::digest(&mmdb));
+  assert!(verify_geo_data("Country.mmdb", &mmdb, Some(checksum)).is_ok());
+  assert!(verify_geo_data("Country.mmdb", &mmdb, Some("0".repeat(64))).is_err());
+
+  let mut dat = vec![0x0au8];
+  dat.extend_from_slice(&[0u8; GEO_DATA_MIN_SIZE]);
+
+  assert!(verify_geo_data("geosite.dat", &dat, None).is_ok());
+  assert!(verify_geo_data("geosite.dat", &dat[1..], None).is_err());
+}
@@ -1,7 +1,12 @@
 mod clash;
+pub mod clash_api;
+mod geo;
+mod overrides;
 mod profiles;
 mod verge;
 
 pub use self::clash::*;
+pub use self::geo::*;
+pub use self::overrides::*;
 pub use self::profiles::*;
 pub use self::verge::*;
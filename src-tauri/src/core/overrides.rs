@@ -0,0 +1,195 @@
+use crate::utils::{config, dirs};
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use serde_yaml::{Mapping, Value};
+
+/// the rules added at runtime
+/// applied to the final config without touching the profiles
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct RuleOverrides {
+  /// route the domains directly by `DOMAIN-SUFFIX`
+  /// insert before all the other rules
+  #[serde(rename = "bypass-domains")]
+  pub bypass_domains: Option<Vec<String>>,
+
+  /// insert before the rules of the profile
+  #[serde(rename = "prepend-rules")]
+  pub prepend_rules: Option<Vec<String>>,
+
+  /// insert before the `MATCH` rule of the profile
+  #[serde(rename = "append-rules")]
+  pub append_rules: Option<Vec<String>>,
+}
+
+impl RuleOverrides {
+  pub fn new() -> Self {
+    config::read_yaml::<RuleOverrides>(dirs::overrides_path())
+  }
+
+  /// save the overrides to the file
+  pub fn save_file(&self) -> Result<()> {
+    config::save_yaml(
+      dirs::overrides_path(),
+      self,
+      Some("# Clash Verge Rule Overrides\n\n"),
+    )
+  }
+
+  /// add a rule like `DOMAIN-SUFFIX,example.com,DIRECT`
+  /// the position should be `prepend` or `append`
+  pub fn add_rule(&mut self, rule: String, position: Option<String>) -> Result<()> {
+    let rule = rule.trim().to_string();
+    let parts = rule.split(',').map(|p| p.trim()).collect::<Vec<&str>>();
+
+    if parts.len() < 2 || parts.iter().any(|p| p.is_empty()) {
+      bail!("invalid rule \"{rule}\"");
+    }
+
+    let list = match position.as_deref() {
+      None | Some("prepend") => &mut self.prepend_rules,
+      Some("append") => &mut self.append_rules,
+      Some(position) => bail!("invalid position \"{position}\""),
+    };
+
+    let list = list.get_or_insert_with(Vec::new);
+    if !list.contains(&rule) {
+      list.push(rule);
+    }
+
+    self.save_file()
+  }
+
+  /// add the domain like `example.com` to bypass the proxy
+  pub fn add_bypass_domain(&mut self, domain: String) -> Result<()> {
+    let domain = RuleOverrides::normalize_domain(&domain)?;
+
+    let list = self.bypass_domains.get_or_insert_with(Vec::new);
+    if !list.contains(&domain) {
+      list.push(domain);
+    }
+
+    self.save_file()
+  }
+
+  /// remove the domain from the bypass list
+  pub fn remove_bypass_domain(&mut self, domain: String) -> Result<()> {
+    let domain = RuleOverrides::normalize_domain(&domain)?;
+
+    let list = self.bypass_domains.get_or_insert_with(Vec::new);
+    match list.iter().position(|d| *d == domain) {
+      Some(index) => list.remove(index),
+      None => bail!("the domain \"{domain}\" is not bypassed"),
+    };
+
+    self.save_file()
+  }
+
+  /// the `*.` and the leading dot make no difference for `DOMAIN-SUFFIX`
+  fn normalize_domain(domain: &str) -> Result<String> {
+    let domain = domain
+      .trim()
+      .trim_start_matches("*.")
+      .trim_start_matches('.');
+    let domain = domain.to_ascii_lowercase();
+
+    if domain.is_empty() || domain.contains(',') || domain.contains(char::is_whitespace) {
+      bail!("invalid domain \"{domain}\"");
+    }
+
+    Ok(domain)
+  }
+
+  /// remove all the overrides
+  pub fn clear(&mut self) -> Result<()> {
+    self.bypass_domains = None;
+    self.prepend_rules = None;
+    self.append_rules = None;
+    self.save_file()
+  }
+
+  /// whether there is nothing to apply
+  pub fn is_empty(&self) -> bool {
+    self.bypass_domains.as_ref().map_or(true, |l| l.is_empty())
+      && self.prepend_rules.as_ref().map_or(true, |l| l.is_empty())
+      && self.append_rules.as_ref().map_or(true, |l| l.is_empty())
+  }
+
+  /// apply the overrides to the config
+  pub fn apply(&self, mut config: Mapping) -> Mapping {
+    if self.is_empty() {
+      return config;
+    }
+
+    let key = Value::from("rules");
+    let mut rules = match config.get(&key) {
+      Some(Value::Sequence(rules)) => rules.clone(),
+      _ => vec![],
+    };
+
+    // the rules after `MATCH` never work
+    let is_match = |rule: &Value| {
+      rule
+        .as_str()
+        .map_or(false, |r| r.trim().to_uppercase().starts_with("MATCH,"))
+    };
+    let mut index = match rules.last() {
+      Some(last) if is_match(last) => rules.len() - 1,
+      _ => rules.len(),
+    };
+
+    for rule in self.append_rules.iter().flatten() {
+      rules.insert(index, Value::from(rule.as_str()));
+      index += 1;
+    }
+
+    let bypass = self.bypass_domains.iter().flatten();
+    let bypass = bypass.map(|domain| Value::from(format!("DOMAIN-SUFFIX,{domain},DIRECT")));
+
+    let prepend = self.prepend_rules.iter().flatten();
+    let prepend = bypass.chain(prepend.map(|rule| Value::from(rule.as_str())));
+    let rules = prepend.chain(rules.into_iter()).collect::<Vec<Value>>();
+
+    config.insert(key, Value::Sequence(rules));
+    config
+  }
+}
+
+#[test]
+fn test_apply_overrides() {
+  let overrides = RuleOverrides {
+    bypass_domains: Some(vec!["d.com".into()]),
+    prepend_rules: Some(vec!["DOMAIN-SUFFIX,a.com,DIRECT".into()]),
+    append_rules: Some(vec!["DOMAIN-SUFFIX,b.com,REJECT".into()]),
+  };
+
+  let config =
+    serde_yaml::from_str::<Mapping>("rules:\n  - DOMAIN,c.com,Proxy\n  - MATCH,Proxy\n").unwrap();
+
+  let get_rules = |config: Mapping| {
+    let rules = config.get(&Value::from("rules")).unwrap();
+    let rules = rules.as_sequence().unwrap().iter();
+    rules
+      .map(|r| r.as_str().unwrap().to_string())
+      .collect::<Vec<_>>()
+  };
+
+  assert_eq!(
+    get_rules(overrides.apply(config)),
+    [
+      "DOMAIN-SUFFIX,d.com,DIRECT",
+      "DOMAIN-SUFFIX,a.com,DIRECT",
+      "DOMAIN,c.com,Proxy",
+      "DOMAIN-SUFFIX,b.com,REJECT",
+      "MATCH,Proxy",
+    ]
+  );
+
+  assert_eq!(get_rules(overrides.apply(Mapping::new())).len(), 3);
+
+  assert_eq!(
+    RuleOverrides::normalize_domain(" *.Example.com").unwrap(),
+    "example.com"
+  );
+  assert!(RuleOverrides::normalize_domain("a.com,REJECT").is_err());
+  assert!(RuleOverrides::normalize_domain("*.").is_err());
+}
@@ -1,47 +1,150 @@
 use crate::log_if_err;
 use crate::{
   core::Clash,
-  utils::{config, dirs, sysopt::SysProxyConfig},
+  utils::{config, dirs, help, init, scheme, server, sysopt::SysProxyConfig},
 };
 use anyhow::{bail, Result};
 use auto_launch::{AutoLaunch, AutoLaunchBuilder};
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+  collections::HashMap,
+  fs,
+  sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+  },
+};
 use tauri::{async_runtime::Mutex, utils::platform::current_exe};
 
+/// whether the system proxy is toggled without changing the config
+/// the guard should not restore it
+static SYSPROXY_TOGGLED: AtomicBool = AtomicBool::new(false);
+
+/// keep the log files in a year at most
+const LOG_DAYS_LIMIT: u64 = 365;
+
 /// ### `verge.yaml` schema
 #[derive(Default, Debug, Clone, Deserialize, Serialize)]
 pub struct VergeConfig {
   // i18n
   pub language: Option<String>,
 
-  /// `light` or `dark`
+  /// `light` `dark` or `system`
+  /// the `system` mode follows the os theme
+  /// the native window effect is tinted by it too
   pub theme_mode: Option<String>,
 
   /// enable blur mode
   /// maybe be able to set the alpha
   pub theme_blur: Option<bool>,
 
+  /// the window effect on windows
+  /// `blur` `acrylic` or `none`, default is `blur`
+  /// applied on the next launch
+  pub window_effect: Option<String>,
+
   /// enable traffic graph default is true
   pub traffic_graph: Option<bool>,
 
+  /// app log level, default is `info`
+  /// `error` `warn` `info` `debug` `trace`
+  /// the `trace` level may log the sensitive urls
+  pub log_level: Option<String>,
+
+  /// keep the log files for days, default is 7
+  pub max_log_days: Option<u64>,
+
   /// clash tun mode
   pub enable_tun_mode: Option<bool>,
 
+  /// keep the system proxy in tun mode
+  /// default is false, the system proxy is cleared
+  pub sysproxy_with_tun: Option<bool>,
+
+  /// restart the clash core automatically if it crashes
+  pub auto_restart_core: Option<bool>,
+
+  /// pick the free ports if the clash ports are occupied
+  pub auto_pick_ports: Option<bool>,
+
+  /// the proxy of the app's own requests
+  /// like `http://127.0.0.1:7890`, use the clash core if empty
+  pub app_proxy: Option<String>,
+
+  /// the source of the geo databases
+  /// the `{file}` is replaced with the file name, use the default if empty
+  pub geo_data_url: Option<String>,
+
+  /// handle the `clash://` links by the app
+  /// default is false, not to take over the other clients
+  pub register_scheme: Option<bool>,
+
+  /// the singleton could be disabled
+  /// default is true
+  pub enable_singleton: Option<bool>,
+
+  /// the port of the embed server
+  /// used by the singleton and the pac script
+  pub server_port: Option<u16>,
+
+  /// the last used clash mode
+  /// re-applied after activating the profile
+  pub clash_mode: Option<String>,
+
   /// can the app auto startup
   pub enable_auto_launch: Option<bool>,
 
+  /// start the app to the tray without showing the window
+  pub silent_start: Option<bool>,
+
   /// set system proxy
   pub enable_system_proxy: Option<bool>,
 
+  /// reject all the traffic by the core
+  /// the mode could not be changed until released
+  pub enable_kill_switch: Option<bool>,
+
   /// enable proxy guard
+  /// re-assert the system proxy if other apps change it
   pub enable_proxy_guard: Option<bool>,
 
+  /// reset the system proxy when the app quits
+  /// default is true
+  /// if false, the system proxy still points to the stopped core,
+  /// so the network is unavailable until the app starts again
+  /// or the system proxy is turned off manually
+  pub reset_proxy_on_exit: Option<bool>,
+
   /// set system proxy bypass
   pub system_proxy_bypass: Option<String>,
 
+  /// the protocols of the system proxy
+  /// `all` `http` or `socks`, default is `all`
+  pub system_proxy_protocol: Option<String>,
+
   /// proxy guard duration
   pub proxy_guard_duration: Option<u64>,
+
+  /// system proxy mode
+  /// `global` or `pac`, default is `global`
+  pub proxy_mode: Option<String>,
+
+  /// clash core
+  /// `clash` or `clash-meta`, default is `clash`
+  pub core: Option<String>,
+
+  /// the dashboard to open
+  /// the hostname/port/secret would be appended to the query
+  pub dashboard_url: Option<String>,
+
+  /// the editor to open the profile
+  /// the path or the command name
+  pub default_editor: Option<String>,
+
+  /// global hotkeys
+  /// map the action to the accelerator like `CmdOrControl+Shift+P`
+  /// actions: `toggle_system_proxy` `toggle_tun_mode` `switch_mode` `toggle_window`
+  pub hotkeys: Option<HashMap<String, String>>,
 }
 
 impl VergeConfig {
@@ -57,6 +160,70 @@ impl VergeConfig {
       Some("# The Config for Clash Verge App\n\n"),
     )
   }
+
+  /// whether the native parts should be dark
+  /// resolve the `system` mode by the os theme
+  pub fn is_dark(&self) -> bool {
+    match self.theme_mode.as_deref() {
+      Some("dark") => true,
+      Some("system") => help::system_dark(),
+      _ => false,
+    }
+  }
+
+  /// merge the fields without side effects
+  /// the `None` ones are left unchanged
+  fn merge(&mut self, patch: &VergeConfig) {
+    macro_rules! merge {
+      ($($key: ident),*) => {
+        $(
+          if patch.$key.is_some() {
+            self.$key = patch.$key.clone();
+          }
+        )*
+      };
+    }
+
+    merge!(
+      language,
+      theme_mode,
+      theme_blur,
+      reset_proxy_on_exit,
+      window_effect,
+      traffic_graph,
+      silent_start,
+      dashboard_url,
+      default_editor,
+      max_log_days,
+      enable_proxy_guard,
+      proxy_guard_duration,
+      auto_restart_core,
+      auto_pick_ports,
+      enable_singleton,
+      server_port,
+      clash_mode,
+      core,
+      hotkeys,
+      geo_data_url,
+      enable_kill_switch
+    );
+  }
+}
+
+/// the auto launch registration of the current exe
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchStatus {
+  /// the `enable_auto_launch` config
+  pub enable: bool,
+
+  /// the registered launch command
+  pub registered: Option<String>,
+
+  /// the launch command of the current exe
+  pub current: String,
+
+  /// the registered one launches the current exe
+  pub matched: bool,
 }
 
 /// Verge App abilities
@@ -99,15 +266,27 @@ impl Verge {
   /// init the sysproxy
   pub fn init_sysproxy(&mut self, port: Option<String>) {
     if let Some(port) = port {
-      let enable = self.config.enable_system_proxy.clone().unwrap_or(false);
-
-      self.old_sysproxy = match SysProxyConfig::get_sys() {
-        Ok(proxy) => Some(proxy),
-        Err(_) => None,
+      let enable = Verge::sysproxy_enabled(&self.config);
+
+      self.old_sysproxy = match Verge::read_guard_file() {
+        // the last run did not clean up
+        // restore the original settings first
+        Some(proxy) => {
+          log::warn!("restore the system proxy left by the last run");
+          log_if_err!(proxy.set_sys());
+          Some(proxy)
+        }
+        None => match SysProxyConfig::get_sys() {
+          Ok(proxy) => Some(proxy),
+          Err(_) => None,
+        },
       };
 
-      let bypass = self.config.system_proxy_bypass.clone();
-      let sysproxy = SysProxyConfig::new(enable, port, bypass);
+      if let Some(proxy) = self.old_sysproxy.as_ref() {
+        log_if_err!(config::save_yaml(dirs::sysproxy_guard_path(), proxy, None));
+      }
+
+      let sysproxy = Verge::gen_sysproxy(&self.config, enable, port);
 
       if enable {
         if sysproxy.set_sys().is_err() {
@@ -122,18 +301,171 @@ impl Verge {
     Verge::guard_proxy(self.guard_state.clone());
   }
 
+  /// reload the config from the file
+  /// then apply the system proxy and startup again
+  pub fn reload_config(&mut self, port: Option<String>) -> Result<()> {
+    let config = VergeConfig::new();
+
+    self.update_launch(config.enable_auto_launch.unwrap_or(false))?;
+    log_if_err!(init::set_log_level(config.log_level.as_ref()));
+    self.config = config;
+
+    if let Some(port) = port {
+      let enable = Verge::sysproxy_enabled(&self.config);
+      let was_enable = self.cur_sysproxy.as_ref().map_or(false, |s| s.enable);
+
+      let sysproxy = Verge::gen_sysproxy(&self.config, enable, port);
+
+      if (enable || was_enable) && sysproxy.set_sys().is_err() {
+        log::error!("failed to set system proxy");
+        bail!("failed to set system proxy");
+      }
+
+      self.cur_sysproxy = Some(sysproxy);
+    }
+
+    Verge::guard_proxy(self.guard_state.clone());
+    Ok(())
+  }
+
+  /// update the port of the system proxy
+  /// after the clash port is changed
+  pub fn update_port(&mut self, port: Option<String>) -> Result<()> {
+    if let (Some(port), Some(sysproxy)) = (port, self.cur_sysproxy.as_mut()) {
+      let port = match sysproxy.protocols() {
+        (false, true) => Clash::read_socks_port().unwrap_or(port),
+        _ => port,
+      };
+      sysproxy.server = format!("127.0.0.1:{port}");
+
+      if sysproxy.enable {
+        sysproxy.set_sys()?;
+      }
+    }
+
+    Ok(())
+  }
+
+  /// whether the system proxy should be set
+  /// it is skipped in tun mode unless `sysproxy_with_tun`
+  pub fn sysproxy_enabled(config: &VergeConfig) -> bool {
+    let enable = config.enable_system_proxy.unwrap_or(false);
+    let tun_mode = config.enable_tun_mode.unwrap_or(false);
+    let with_tun = config.sysproxy_with_tun.unwrap_or(false);
+
+    enable && (!tun_mode || with_tun)
+  }
+
+  /// generate the system proxy with the verge config
+  /// the socks only proxy prefers the socks port of the core
+  fn gen_sysproxy(config: &VergeConfig, enable: bool, port: String) -> SysProxyConfig {
+    let protocol = config.system_proxy_protocol.clone();
+    let port = match protocol.as_deref() {
+      Some("socks") => Clash::read_socks_port().unwrap_or(port),
+      _ => port,
+    };
+
+    let bypass = config.system_proxy_bypass.clone();
+    let mut sysproxy = SysProxyConfig::new(enable, port, bypass);
+    sysproxy.pac_url = Verge::pac_url(config);
+    sysproxy.protocol = protocol;
+    sysproxy
+  }
+
+  /// get the pac url if the proxy mode is `pac`
+  fn pac_url(config: &VergeConfig) -> Option<String> {
+    match config.proxy_mode.as_ref() {
+      Some(mode) if mode == "pac" => Some(server::pac_url()),
+      _ => None,
+    }
+  }
+
+  /// turn on/off the system proxy without changing the config
+  /// it is restored by the config on the next start
+  pub fn toggle_sysproxy(&mut self, enable: bool) -> Result<()> {
+    let sysproxy = match self.cur_sysproxy.as_mut() {
+      Some(sysproxy) => sysproxy,
+      None => bail!("the system proxy is not initialized"),
+    };
+
+    let old_enable = sysproxy.enable;
+    sysproxy.enable = enable;
+
+    if sysproxy.set_sys().is_err() {
+      sysproxy.enable = old_enable;
+
+      log::error!("failed to set system proxy");
+      bail!("failed to set system proxy");
+    }
+
+    let toggled = enable != Verge::sysproxy_enabled(&self.config);
+    SYSPROXY_TOGGLED.store(toggled, Ordering::SeqCst);
+
+    Ok(())
+  }
+
   /// reset the sysproxy
   pub fn reset_sysproxy(&mut self) {
     if let Some(sysproxy) = self.old_sysproxy.take() {
       match sysproxy.set_sys() {
-        Ok(_) => self.cur_sysproxy = None,
+        Ok(_) => {
+          self.cur_sysproxy = None;
+          let _ = fs::remove_file(dirs::sysproxy_guard_path());
+        }
         Err(_) => log::error!("failed to reset proxy for"),
       }
     }
   }
 
+  /// read the original system proxy saved by the last run
+  /// which exists only if the app exits unexpectedly
+  fn read_guard_file() -> Option<SysProxyConfig> {
+    let yaml_str = fs::read_to_string(dirs::sysproxy_guard_path()).ok()?;
+    serde_yaml::from_str::<SysProxyConfig>(&yaml_str).ok()
+  }
+
   /// init the auto launch
+  /// re-register it if the app has been moved
   pub fn init_launch(&mut self) -> Result<()> {
+    let (app_name, app_path) = Verge::launch_target();
+
+    let auto = AutoLaunchBuilder::new()
+      .set_app_name(&app_name)
+      .set_app_path(&app_path)
+      .build();
+
+    if let Some(enable) = self.config.enable_auto_launch.as_ref() {
+      // fix issue #26
+      if *enable {
+        let registered = Verge::read_launch_path(&app_name);
+
+        if !Verge::launch_matched(registered.as_deref(), &app_path) {
+          log::info!("register the auto launch for \"{app_path}\", the old one is {registered:?}");
+          auto.enable()?;
+        }
+      }
+    }
+
+    self.auto_launch = Some(auto);
+
+    Ok(())
+  }
+
+  /// get the status of the auto launch registration
+  pub fn launch_status(&self) -> LaunchStatus {
+    let (app_name, app_path) = Verge::launch_target();
+    let registered = Verge::read_launch_path(&app_name);
+
+    LaunchStatus {
+      enable: self.config.enable_auto_launch.unwrap_or(false),
+      matched: Verge::launch_matched(registered.as_deref(), &app_path),
+      registered,
+      current: app_path,
+    }
+  }
+
+  /// the app name and the launch command of the current exe
+  fn launch_target() -> (String, String) {
     let app_exe = current_exe().unwrap();
     let app_exe = dunce::canonicalize(app_exe).unwrap();
     let app_name = app_exe.file_stem().unwrap().to_str().unwrap();
@@ -142,24 +474,71 @@ impl Verge {
     // fix issue #26
     #[cfg(target_os = "windows")]
     let app_path = format!("\"{app_path}\"");
-    #[cfg(target_os = "windows")]
-    let app_path = app_path.as_str();
 
-    let auto = AutoLaunchBuilder::new()
-      .set_app_name(app_name)
-      .set_app_path(app_path)
-      .build();
+    (app_name.to_string(), app_path.to_string())
+  }
 
-    if let Some(enable) = self.config.enable_auto_launch.as_ref() {
-      // fix issue #26
-      if *enable {
-        auto.enable()?;
+  /// whether the registered command launches the current exe
+  fn launch_matched(registered: Option<&str>, app_path: &str) -> bool {
+    let normalize = |path: &str| {
+      let path = path.trim().replace('"', "");
+
+      // the path is case insensitive on windows
+      match cfg!(target_os = "windows") {
+        true => path.to_lowercase(),
+        false => path,
       }
+    };
+
+    match registered {
+      Some(registered) => normalize(registered).starts_with(&normalize(app_path)),
+      None => false,
     }
+  }
 
-    self.auto_launch = Some(auto);
+  /// read the registered launch command
+  /// the `Run` key in the registry
+  #[cfg(target_os = "windows")]
+  fn read_launch_path(app_name: &str) -> Option<String> {
+    use winreg::{enums::HKEY_CURRENT_USER, RegKey};
 
-    Ok(())
+    let key = RegKey::predef(HKEY_CURRENT_USER)
+      .open_subkey("Software\\Microsoft\\Windows\\CurrentVersion\\Run")
+      .ok()?;
+    key.get_value::<String, _>(app_name).ok()
+  }
+
+  /// read the registered launch command
+  /// the login item of the system events
+  #[cfg(target_os = "macos")]
+  fn read_launch_path(app_name: &str) -> Option<String> {
+    use std::process::Command;
+
+    let script = format!(
+      "tell application \"System Events\" to get the path of every login item whose name is \"{app_name}\""
+    );
+    let output = Command::new("osascript")
+      .args(["-e", &script])
+      .output()
+      .ok()?;
+    let path = String::from_utf8(output.stdout).ok()?;
+    let path = path.split(", ").next()?.trim();
+
+    (!path.is_empty()).then(|| path.to_string())
+  }
+
+  /// read the registered launch command
+  /// the `Exec` of the autostart desktop entry
+  #[cfg(target_os = "linux")]
+  fn read_launch_path(app_name: &str) -> Option<String> {
+    let dir = tauri::api::path::config_dir()?;
+    let entry =
+      fs::read_to_string(dir.join("autostart").join(format!("{app_name}.desktop"))).ok()?;
+
+    entry
+      .lines()
+      .find_map(|line| line.strip_prefix("Exec="))
+      .map(|exec| exec.to_string())
   }
 
   /// update the startup
@@ -184,18 +563,20 @@ impl Verge {
   /// There should be only one update at a time here
   /// so call the save_file at the end is savely
   pub fn patch_config(&mut self, patch: VergeConfig) -> Result<()> {
-    // only change it
-    if patch.language.is_some() {
-      self.config.language = patch.language;
-    }
-    if patch.theme_mode.is_some() {
-      self.config.theme_mode = patch.theme_mode;
-    }
-    if patch.theme_blur.is_some() {
-      self.config.theme_blur = patch.theme_blur;
+    // zero would remove the log file being written
+    if let Some(days) = patch.max_log_days {
+      if days == 0 || days > LOG_DAYS_LIMIT {
+        bail!("the max log days should be between 1 and {LOG_DAYS_LIMIT}");
+      }
     }
-    if patch.traffic_graph.is_some() {
-      self.config.traffic_graph = patch.traffic_graph;
+
+    // only change it
+    self.config.merge(&patch);
+
+    // reconfigure the logger without restart
+    if patch.log_level.is_some() {
+      init::set_log_level(patch.log_level.as_ref())?;
+      self.config.log_level = patch.log_level;
     }
 
     // should update system startup
@@ -205,12 +586,25 @@ impl Verge {
       self.config.enable_auto_launch = Some(enable);
     }
 
+    // only register when the user opts in
+    if patch.register_scheme == Some(true) {
+      scheme::register_scheme()?;
+    }
+    if patch.register_scheme.is_some() {
+      self.config.register_scheme = patch.register_scheme;
+    }
+
     // should update system proxy
     if patch.enable_system_proxy.is_some() {
       let enable = patch.enable_system_proxy.unwrap();
+      SYSPROXY_TOGGLED.store(false, Ordering::SeqCst);
+      let config = VergeConfig {
+        enable_system_proxy: Some(enable),
+        ..self.config.clone()
+      };
 
       if let Some(mut sysproxy) = self.cur_sysproxy.take() {
-        sysproxy.enable = enable;
+        sysproxy.enable = Verge::sysproxy_enabled(&config);
         if sysproxy.set_sys().is_err() {
           self.cur_sysproxy = Some(sysproxy);
 
@@ -228,7 +622,7 @@ impl Verge {
 
       if let Some(mut sysproxy) = self.cur_sysproxy.take() {
         if sysproxy.enable {
-          sysproxy.bypass = bypass.clone();
+          sysproxy.bypass = SysProxyConfig::merge_bypass(Some(bypass.clone()));
 
           if sysproxy.set_sys().is_err() {
             self.cur_sysproxy = Some(sysproxy);
@@ -244,13 +638,47 @@ impl Verge {
       self.config.system_proxy_bypass = Some(bypass);
     }
 
-    // proxy guard
-    // only change it
-    if patch.enable_proxy_guard.is_some() {
-      self.config.enable_proxy_guard = patch.enable_proxy_guard;
+    // turn off the previous protocols before changing
+    if patch.system_proxy_protocol.is_some() {
+      self.config.system_proxy_protocol = patch.system_proxy_protocol;
+
+      if let Some(mut sysproxy) = self.cur_sysproxy.take() {
+        if sysproxy.enable {
+          sysproxy.enable = false;
+          log_if_err!(sysproxy.set_sys());
+        }
+
+        let enable = Verge::sysproxy_enabled(&self.config);
+        let port = Clash::read_port().unwrap_or_default();
+        let sysproxy = Verge::gen_sysproxy(&self.config, enable, port);
+
+        if enable && sysproxy.set_sys().is_err() {
+          self.cur_sysproxy = Some(sysproxy);
+
+          log::error!("failed to set system proxy");
+          bail!("failed to set system proxy");
+        }
+
+        self.cur_sysproxy = Some(sysproxy);
+      }
     }
-    if patch.proxy_guard_duration.is_some() {
-      self.config.proxy_guard_duration = patch.proxy_guard_duration;
+
+    // should update system proxy too
+    if patch.proxy_mode.is_some() {
+      self.config.proxy_mode = patch.proxy_mode;
+
+      if let Some(mut sysproxy) = self.cur_sysproxy.take() {
+        sysproxy.pac_url = Verge::pac_url(&self.config);
+
+        if sysproxy.enable && sysproxy.set_sys().is_err() {
+          self.cur_sysproxy = Some(sysproxy);
+
+          log::error!("failed to set system proxy");
+          bail!("failed to set system proxy");
+        }
+
+        self.cur_sysproxy = Some(sysproxy);
+      }
     }
 
     // relaunch the guard
@@ -258,11 +686,37 @@ impl Verge {
       Verge::guard_proxy(self.guard_state.clone());
     }
 
+    if let Some(proxy) = patch.app_proxy {
+      let proxy = proxy.trim().to_string();
+
+      if !proxy.is_empty() && reqwest::Proxy::all(&proxy).is_err() {
+        bail!("invalid app proxy \"{proxy}\"");
+      }
+      self.config.app_proxy = Some(proxy);
+    }
     // handle the tun mode
-    if patch.enable_tun_mode.is_some() {
-      self.config.enable_tun_mode = patch.enable_tun_mode;
+    // the system proxy is redundant in tun mode unless `sysproxy_with_tun`
+    if patch.enable_tun_mode.is_some() || patch.sysproxy_with_tun.is_some() {
+      let was_enable = Verge::sysproxy_enabled(&self.config);
+
+      if patch.enable_tun_mode.is_some() {
+        self.config.enable_tun_mode = patch.enable_tun_mode;
+      }
+      if patch.sysproxy_with_tun.is_some() {
+        self.config.sysproxy_with_tun = patch.sysproxy_with_tun;
+      }
+
+      let enable = Verge::sysproxy_enabled(&self.config);
+
+      if was_enable && !enable {
+        self.reset_sysproxy();
+      } else if !was_enable && enable {
+        self.init_sysproxy(Clash::read_port());
+      }
     }
 
+    // the sidecar should be restarted by the caller
+    // and the hotkeys should be registered by the caller
     self.config.save_file()
   }
 }
@@ -292,7 +746,7 @@ impl Verge {
 
         let verge = Verge::new();
 
-        let enable_proxy = verge.config.enable_system_proxy.unwrap_or(false);
+        let enable_proxy = Verge::sysproxy_enabled(&verge.config);
         let enable_guard = verge.config.enable_proxy_guard.unwrap_or(false);
         let guard_duration = verge.config.proxy_guard_duration.unwrap_or(10);
 
@@ -304,19 +758,33 @@ impl Verge {
           break;
         }
 
-        log::info!("try to guard proxy");
+        // turned off by the user for a while
+        if SYSPROXY_TOGGLED.load(Ordering::SeqCst) {
+          continue;
+        }
 
         let clash = Clash::new();
 
-        match &clash.info.port {
-          Some(port) => {
-            let bypass = verge.config.system_proxy_bypass.clone();
-            let sysproxy = SysProxyConfig::new(true, port.clone(), bypass);
-
-            log_if_err!(sysproxy.set_sys());
+        let sysproxy = match &clash.info.port {
+          Some(port) => Verge::gen_sysproxy(&verge.config, true, port.clone()),
+          None => {
+            log::error!("fail to parse clash port");
+            continue;
           }
-          None => log::error!("fail to parse clash port"),
+        };
+
+        // only re-assert the drifted one
+        // apply it anyway if failed to read the os proxy
+        match SysProxyConfig::get_sys() {
+          Ok(sys) if sysproxy.is_same(&sys) => continue,
+          Ok(sys) => log::info!(
+            "the system proxy drifted to \"{}\", re-assert it",
+            sys.server
+          ),
+          Err(err) => log::warn!("failed to get the system proxy for {err}, re-assert it"),
         }
+
+        log_if_err!(sysproxy.set_sys());
       }
 
       let mut state = guard_state.lock().await;
@@ -324,3 +792,25 @@ impl Verge {
     });
   }
 }
+
+#[test]
+fn test_merge_config() {
+  let mut config = VergeConfig {
+    language: Some("zh".into()),
+    theme_mode: Some("dark".into()),
+    enable_proxy_guard: Some(true),
+    ..VergeConfig::default()
+  };
+
+  config.merge(&VergeConfig {
+    theme_mode: Some("light".into()),
+    ..VergeConfig::default()
+  });
+
+  assert_eq!(config.theme_mode, Some("light".into()));
+  assert_eq!(config.language, Some("zh".into()));
+  assert_eq!(config.enable_proxy_guard, Some(true));
+
+  config.merge(&VergeConfig::default());
+  assert_eq!(config.theme_mode, Some("light".into()));
+}
@@ -8,10 +8,8 @@ mod core;
 mod states;
 mod utils;
 
-use crate::utils::{resolve, server};
-use tauri::{
-  api, CustomMenuItem, Manager, SystemTray, SystemTrayEvent, SystemTrayMenu, SystemTrayMenuItem,
-};
+use crate::utils::{resolve, server, tray, window};
+use tauri::{api, Manager, SystemTray};
 
 fn main() -> std::io::Result<()> {
   if server::check_singleton().is_err() {
@@ -19,78 +17,99 @@ fn main() -> std::io::Result<()> {
     return Ok(());
   }
 
-  let tray_menu = SystemTrayMenu::new()
-    .add_item(CustomMenuItem::new("open_window", "Show"))
-    .add_item(CustomMenuItem::new("restart_clash", "Restart Clash"))
-    .add_native_item(SystemTrayMenuItem::Separator)
-    .add_item(CustomMenuItem::new("quit", "Quit").accelerator("CmdOrControl+Q"));
-
   #[allow(unused_mut)]
   let mut builder = tauri::Builder::default()
     .manage(states::VergeState::default())
     .manage(states::ClashState::default())
     .manage(states::ProfilesState::default())
+    .manage(states::UpdateState::default())
+    .manage(states::LogStreamState::default())
+    .manage(states::ImportState::default())
     .setup(|app| Ok(resolve::resolve_setup(app)))
-    .system_tray(SystemTray::new().with_menu(tray_menu))
-    .on_system_tray_event(move |app_handle, event| match event {
-      SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
-        "open_window" => {
-          let window = app_handle.get_window("main").unwrap();
-          window.unminimize().unwrap();
-          window.show().unwrap();
-          window.set_focus().unwrap();
-        }
-        "restart_clash" => {
-          let clash_state = app_handle.state::<states::ClashState>();
-          let profiles_state = app_handle.state::<states::ProfilesState>();
-          let mut clash = clash_state.0.lock().unwrap();
-          let mut profiles = profiles_state.0.lock().unwrap();
-
-          crate::log_if_err!(clash.restart_sidecar(&mut profiles));
-        }
-        "quit" => {
-          resolve::resolve_reset(app_handle);
-          api::process::kill_children();
-          std::process::exit(0);
-        }
-        _ => {}
-      },
-      SystemTrayEvent::LeftClick { .. } => {
-        if cfg![target_os = "windows"] {
-          let window = app_handle.get_window("main").unwrap();
-          window.unminimize().unwrap();
-          window.show().unwrap();
-          window.set_focus().unwrap();
-        }
-      }
-      _ => {}
-    })
+    .system_tray(SystemTray::new().with_menu(tray::tray_menu()))
+    .on_system_tray_event(tray::on_system_tray_event)
     .invoke_handler(tauri::generate_handler![
       // common
       cmds::restart_sidecar,
+      cmds::update_geo_data,
       cmds::get_sys_proxy,
+      cmds::get_launch_status,
       cmds::get_cur_proxy,
+      cmds::get_sys_proxy_bypass,
       cmds::kill_sidecars,
+      cmds::get_storage_info,
       cmds::open_app_dir,
       cmds::open_logs_dir,
+      cmds::open_profile_template,
+      cmds::reset_profile_template,
+      cmds::backup_config,
+      cmds::restore_config,
+      cmds::reset_all_config,
       // clash
       cmds::get_clash_info,
+      cmds::get_clash_version,
+      cmds::get_core_status,
+      cmds::test_clash_secret,
+      cmds::get_traffic,
+      cmds::get_connections,
+      cmds::close_connection,
+      cmds::close_all_connections,
+      cmds::flush_fakeip,
+      cmds::query_dns,
+      cmds::check_connectivity,
+      cmds::get_providers,
+      cmds::update_provider,
+      cmds::test_group_delay,
+      cmds::open_dashboard,
       cmds::patch_clash_config,
+      cmds::set_kill_switch,
+      cmds::get_interfaces,
+      cmds::get_local_networks,
+      cmds::get_runtime_config,
+      cmds::get_clash_log_level,
+      cmds::set_clash_log_level,
+      cmds::start_log_stream,
+      cmds::stop_log_stream,
+      cmds::reload_clash_config,
+      cmds::get_runtime_config_path,
       // verge
       cmds::get_verge_config,
       cmds::patch_verge_config,
+      cmds::toggle_system_proxy,
       // profile
       cmds::view_profile,
+      cmds::open_profile_home,
+      cmds::reveal_profile,
       cmds::patch_profile,
+      cmds::patch_selected,
       cmds::create_profile,
       cmds::import_profile,
+      cmds::import_profiles_batch,
+      cmds::import_profile_text,
+      cmds::import_profile_file,
+      cmds::import_from_qr,
+      cmds::take_pending_import,
+      cmds::take_launch_error,
       cmds::update_profile,
+      cmds::update_all_profiles,
+      cmds::cancel_update,
       cmds::delete_profile,
       cmds::select_profile,
       cmds::get_profiles,
       cmds::sync_profiles,
       cmds::enhance_profiles,
-      cmds::change_profile_chain
+      cmds::add_rule,
+      cmds::get_rule_overrides,
+      cmds::clear_rule_overrides,
+      cmds::add_bypass_domain,
+      cmds::remove_bypass_domain,
+      cmds::list_bypass_domains,
+      cmds::export_config,
+      cmds::dry_run_profile,
+      cmds::test_enhance_script,
+      cmds::reorder_profiles,
+      cmds::change_profile_chain,
+      cmds::move_profile_chain,
     ]);
 
   #[cfg(target_os = "macos")]
@@ -117,9 +136,13 @@ fn main() -> std::io::Result<()> {
       tauri::RunEvent::CloseRequested { label, api, .. } => {
         let app_handle = app_handle.clone();
         api.prevent_close();
-        app_handle.get_window(&label).unwrap().hide().unwrap();
+
+        let window = app_handle.get_window(&label).unwrap();
+        window::save_window_state(&window);
+        window.hide().unwrap();
       }
       tauri::RunEvent::ExitRequested { .. } => {
+        window::save_main_window_state(app_handle);
         resolve::resolve_reset(app_handle);
         api::process::kill_children();
       }
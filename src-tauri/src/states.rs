@@ -1,5 +1,9 @@
 use crate::core::{Clash, Profiles, Verge};
-use std::sync::{Arc, Mutex};
+use std::{
+  collections::HashMap,
+  sync::{Arc, Mutex},
+};
+use tokio::sync::oneshot;
 
 #[derive(Default)]
 pub struct ProfilesState(pub Arc<Mutex<Profiles>>);
@@ -9,3 +13,16 @@ pub struct ClashState(pub Arc<Mutex<Clash>>);
 
 #[derive(Default)]
 pub struct VergeState(pub Arc<Mutex<Verge>>);
+
+/// the cancel senders of the running profile fetches
+/// keyed by the profile uid or the import url
+#[derive(Default)]
+pub struct UpdateState(pub Arc<Mutex<HashMap<String, oneshot::Sender<()>>>>);
+
+/// the url of the deep link waiting for the user's confirmation
+#[derive(Default)]
+pub struct ImportState(pub Arc<Mutex<Option<String>>>);
+
+/// stop the running log stream by dropping the sender
+#[derive(Default)]
+pub struct LogStreamState(pub Arc<Mutex<Option<oneshot::Sender<()>>>>);
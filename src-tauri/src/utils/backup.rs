@@ -0,0 +1,140 @@
+use super::dirs;
+use crate::core::{Profiles, VergeConfig};
+use anyhow::{bail, Context, Result};
+use serde_yaml::Mapping;
+use std::{
+  collections::HashMap,
+  fs,
+  io::{Read, Write},
+  path::{Path, PathBuf},
+};
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+/// these files must exist in the backup
+const REQUIRED_FILES: [&str; 3] = ["config.yaml", "verge.yaml", "profiles.yaml"];
+
+/// the logs are useless on the other machine
+const IGNORED_DIRS: [&str; 1] = ["logs"];
+/// the original system proxy of this machine
+const IGNORED_FILES: [&str; 1] = ["sysproxy-guard.yaml"];
+
+/// zip all the config files under the app home dir
+/// including the profiles and the clash secret
+pub fn create_backup(target: PathBuf) -> Result<()> {
+  let home_dir = dirs::app_home_dir();
+  let file = fs::File::create(&target).context(format!(
+    "failed to create the file \"{}\"",
+    target.display()
+  ))?;
+
+  let mut writer = ZipWriter::new(file);
+
+  for path in collect_files(&home_dir)? {
+    // the zip entry always uses `/`
+    let name = path
+      .strip_prefix(&home_dir)?
+      .components()
+      .map(|c| c.as_os_str().to_string_lossy().to_string())
+      .collect::<Vec<String>>()
+      .join("/");
+
+    writer.start_file(name, FileOptions::default())?;
+    writer.write_all(&fs::read(&path)?)?;
+  }
+
+  writer.finish()?;
+  Ok(())
+}
+
+/// validate the backup and unpack it to the app home dir
+/// nothing would be changed if the backup is invalid
+pub fn restore_backup(source: PathBuf) -> Result<()> {
+  let entries = read_backup(&source)?;
+  let home_dir = dirs::app_home_dir();
+
+  for (name, data) in entries.iter() {
+    let path = home_dir.join(name);
+
+    if let Some(parent) = path.parent() {
+      fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, data).context(format!("failed to restore the file \"{name}\""))?;
+  }
+
+  Ok(())
+}
+
+/// read all the entries of the backup into memory
+/// refuse the partial or corrupt backup
+fn read_backup(source: &Path) -> Result<HashMap<String, Vec<u8>>> {
+  let file =
+    fs::File::open(source).context(format!("failed to open the file \"{}\"", source.display()))?;
+  let mut archive = ZipArchive::new(file).context("invalid backup archive")?;
+  let mut entries = HashMap::new();
+
+  for index in 0..archive.len() {
+    let mut entry = archive.by_index(index)?;
+
+    if entry.is_dir() {
+      continue;
+    }
+
+    // avoid writing the files outside the app home dir
+    let name = match entry.enclosed_name() {
+      Some(path) => path.to_string_lossy().replace('\\', "/"),
+      None => bail!("invalid file path \"{}\" in the backup", entry.name()),
+    };
+
+    // fail on the crc mismatch
+    let mut data = vec![];
+    entry
+      .read_to_end(&mut data)
+      .context(format!("the file \"{name}\" in the backup is corrupt"))?;
+
+    entries.insert(name, data);
+  }
+
+  for name in REQUIRED_FILES {
+    if !entries.contains_key(name) {
+      bail!("the backup is incomplete, missing \"{name}\"");
+    }
+  }
+
+  serde_yaml::from_slice::<Mapping>(&entries["config.yaml"]).context("invalid config.yaml")?;
+  serde_yaml::from_slice::<VergeConfig>(&entries["verge.yaml"]).context("invalid verge.yaml")?;
+  let profiles = serde_yaml::from_slice::<Profiles>(&entries["profiles.yaml"])
+    .context("invalid profiles.yaml")?;
+
+  // every profile file should be included
+  for item in profiles.get_items().iter() {
+    if let Some(file) = item.file.as_ref() {
+      let name = format!("profiles/{file}");
+      if !entries.contains_key(&name) {
+        bail!("the backup is incomplete, missing \"{name}\"");
+      }
+    }
+  }
+
+  Ok(entries)
+}
+
+/// list all the files to backup
+fn collect_files(dir: &Path) -> Result<Vec<PathBuf>> {
+  let mut files = vec![];
+
+  for entry in fs::read_dir(dir)? {
+    let path = entry?.path();
+
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+
+    if path.is_dir() {
+      if !IGNORED_DIRS.contains(&name.as_ref()) {
+        files.extend(collect_files(&path)?);
+      }
+    } else if !IGNORED_FILES.contains(&name.as_ref()) {
+      files.push(path);
+    }
+  }
+
+  Ok(files)
+}
@@ -1,7 +1,10 @@
-use std::env::temp_dir;
+use serde::Serialize;
+use std::env::{temp_dir, var_os};
+use std::fs;
 use std::path::{Path, PathBuf};
 use tauri::{
   api::path::{home_dir, resource_dir},
+  utils::platform::current_exe,
   Env, PackageInfo,
 };
 
@@ -14,15 +17,51 @@ static CLASH_CONFIG: &str = "config.yaml";
 static VERGE_CONFIG: &str = "verge.yaml";
 static PROFILE_YAML: &str = "profiles.yaml";
 static PROFILE_TEMP: &str = "clash-verge-runtime.yaml";
+static DRY_RUN_TEMP: &str = "clash-verge-dry-run.yaml";
+static STARTUP_TEMP: &str = "clash-verge-startup.yaml";
+static WINDOW_STATE: &str = "window-state.json";
+static SYSPROXY_GUARD: &str = "sysproxy-guard.yaml";
+static RULE_OVERRIDES: &str = "overrides.yaml";
+static PROFILE_TEMPLATE: &str = "profile-template.yaml";
+static SERVER_TOKEN: &str = "server-token";
+
+/// use the custom data dir if it is set
+/// the relative path is based on the exe dir
+static DATA_DIR_ENV: &str = "CLASH_VERGE_DATA_DIR";
+/// the portable mode uses the `data` dir next to the exe
+static PORTABLE_FLAG: &str = ".portable";
+static PORTABLE_DIR: &str = "data";
 
 /// get the verge app home dir
 pub fn app_home_dir() -> PathBuf {
+  if let Some(dir) = var_os(DATA_DIR_ENV).filter(|dir| !dir.is_empty()) {
+    let dir = PathBuf::from(dir);
+
+    return match (dir.is_relative(), app_exe_dir()) {
+      (true, Some(exe_dir)) => exe_dir.join(dir),
+      _ => dir,
+    };
+  }
+
+  if let Some(exe_dir) = app_exe_dir() {
+    if exe_dir.join(PORTABLE_FLAG).exists() {
+      return exe_dir.join(PORTABLE_DIR);
+    }
+  }
+
   home_dir()
     .unwrap()
     .join(Path::new(".config"))
     .join(Path::new(APP_DIR))
 }
 
+/// get the dir of the app exe
+fn app_exe_dir() -> Option<PathBuf> {
+  let app_exe = current_exe().ok()?;
+  let app_exe = dunce::canonicalize(app_exe).ok()?;
+  app_exe.parent().map(|dir| dir.to_path_buf())
+}
+
 /// get the resources dir
 pub fn app_resources_dir(package_info: &PackageInfo) -> PathBuf {
   resource_dir(package_info, &Env::default())
@@ -55,3 +94,87 @@ pub fn profiles_path() -> PathBuf {
 pub fn profiles_temp_path() -> PathBuf {
   temp_dir().join(PROFILE_TEMP)
 }
+
+pub fn dry_run_temp_path() -> PathBuf {
+  temp_dir().join(DRY_RUN_TEMP)
+}
+
+pub fn startup_temp_path() -> PathBuf {
+  temp_dir().join(STARTUP_TEMP)
+}
+
+pub fn window_state_path() -> PathBuf {
+  app_home_dir().join(WINDOW_STATE)
+}
+
+pub fn sysproxy_guard_path() -> PathBuf {
+  app_home_dir().join(SYSPROXY_GUARD)
+}
+
+pub fn overrides_path() -> PathBuf {
+  app_home_dir().join(RULE_OVERRIDES)
+}
+
+pub fn profile_template_path() -> PathBuf {
+  app_home_dir().join(PROFILE_TEMPLATE)
+}
+
+pub fn server_token_path() -> PathBuf {
+  app_home_dir().join(SERVER_TOKEN)
+}
+
+/// the disk usage of a dir
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct DirUsage {
+  pub path: String,
+
+  /// the count of the files
+  pub files: usize,
+
+  /// the total size of the files
+  pub bytes: u64,
+}
+
+/// the disk usage of the app dirs
+#[derive(Default, Debug, Clone, Serialize)]
+pub struct StorageInfo {
+  pub profiles: DirUsage,
+
+  pub logs: DirUsage,
+}
+
+/// walk the dir to count the files and sizes
+/// the unreadable entries are skipped
+pub fn dir_usage(dir: &Path) -> DirUsage {
+  let mut usage = DirUsage {
+    path: dir.to_string_lossy().to_string(),
+    ..DirUsage::default()
+  };
+  let mut stack = vec![dir.to_path_buf()];
+
+  while let Some(dir) = stack.pop() {
+    let entries = match fs::read_dir(&dir) {
+      Ok(entries) => entries,
+      Err(err) => {
+        log::debug!("failed to read the dir \"{}\" for {err}", dir.display());
+        continue;
+      }
+    };
+
+    for entry in entries.flatten() {
+      let metadata = match entry.metadata() {
+        Ok(metadata) => metadata,
+        Err(_) => continue,
+      };
+
+      if metadata.is_dir() {
+        stack.push(entry.path());
+      } else if metadata.is_file() {
+        usage.files += 1;
+        usage.bytes += metadata.len();
+      }
+    }
+  }
+
+  usage
+}
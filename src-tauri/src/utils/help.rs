@@ -1,4 +1,9 @@
+use anyhow::{bail, Result};
 use nanoid::nanoid;
+use network_interface::{Addr, NetworkInterface, NetworkInterfaceConfig};
+use reqwest::Url;
+use std::net::{Ipv4Addr, TcpListener};
+use std::path::Path;
 use std::str::FromStr;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -41,6 +46,230 @@ pub fn parse_str<T: FromStr>(target: &str, key: &str) -> Option<T> {
   }
 }
 
+/// check whether the local port is available
+/// by trying to bind it
+pub fn port_available(port: u16) -> bool {
+  TcpListener::bind(("127.0.0.1", port)).is_ok() && TcpListener::bind(("0.0.0.0", port)).is_ok()
+}
+
+/// the names of the network interfaces
+pub fn interface_names() -> Result<Vec<String>> {
+  let list = NetworkInterface::show()?;
+  let mut names = list.into_iter().map(|i| i.name).collect::<Vec<_>>();
+
+  names.sort();
+  names.dedup();
+  Ok(names)
+}
+
+/// the ipv4 cidrs of the local networks like `192.168.1.0/24`
+/// skip the loopback, the link-local and the down interfaces
+/// see `up_interfaces` for the state of the interface
+pub fn local_networks() -> Result<Vec<String>> {
+  let mut list = vec![];
+  let up = up_interfaces();
+
+  for iface in NetworkInterface::show()?.into_iter() {
+    if !up.as_ref().map_or(true, |up| up.contains(&iface.name)) {
+      continue;
+    }
+
+    if let Some(Addr::V4(addr)) = iface.addr {
+      if addr.ip.is_loopback() || addr.ip.is_link_local() {
+        continue;
+      }
+
+      if let Some(netmask) = addr.netmask {
+        let cidr = to_cidr(addr.ip, netmask);
+
+        if !list.contains(&cidr) {
+          list.push(cidr);
+        }
+      }
+    }
+  }
+
+  Ok(list)
+}
+
+/// whether the os prefers the dark theme
+#[cfg(target_os = "windows")]
+pub fn system_dark() -> bool {
+  use winreg::{enums::HKEY_CURRENT_USER, RegKey};
+
+  let path = "Software\\Microsoft\\Windows\\CurrentVersion\\Themes\\Personalize";
+  let light = RegKey::predef(HKEY_CURRENT_USER)
+    .open_subkey(path)
+    .and_then(|key| key.get_value::<u32, _>("AppsUseLightTheme"));
+
+  matches!(light, Ok(0))
+}
+
+/// whether the os prefers the dark theme
+#[cfg(target_os = "macos")]
+pub fn system_dark() -> bool {
+  let output = std::process::Command::new("defaults")
+    .args(["read", "-g", "AppleInterfaceStyle"])
+    .output();
+
+  output.map_or(false, |o| {
+    String::from_utf8_lossy(&o.stdout).trim() == "Dark"
+  })
+}
+
+/// whether the os prefers the dark theme
+/// read the gnome setting which the most desktops follow
+#[cfg(target_os = "linux")]
+pub fn system_dark() -> bool {
+  let output = std::process::Command::new("gsettings")
+    .args(["get", "org.gnome.desktop.interface", "color-scheme"])
+    .output();
+
+  output.map_or(false, |o| {
+    String::from_utf8_lossy(&o.stdout).contains("dark")
+  })
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn system_dark() -> bool {
+  false
+}
+
+/// the names of the interfaces which are up and running
+/// `network-interface` does not report the state of the interface
+/// all are treated as up if the state could not be read
+#[cfg(unix)]
+fn up_interfaces() -> Option<Vec<String>> {
+  use std::ffi::CStr;
+
+  let mut addrs = std::ptr::null_mut();
+  if unsafe { libc::getifaddrs(&mut addrs) } != 0 {
+    return None;
+  }
+
+  let up = libc::IFF_UP | libc::IFF_RUNNING;
+  let mut names = vec![];
+  let mut cursor = addrs;
+
+  while !cursor.is_null() {
+    let ifa = unsafe { &*cursor };
+
+    if ifa.ifa_flags as libc::c_int & up == up {
+      let name = unsafe { CStr::from_ptr(ifa.ifa_name) };
+      let name = name.to_string_lossy().to_string();
+
+      if !names.contains(&name) {
+        names.push(name);
+      }
+    }
+    cursor = ifa.ifa_next;
+  }
+
+  unsafe { libc::freeifaddrs(addrs) };
+  Some(names)
+}
+
+/// the names are the friendly ones same as `network-interface`
+#[cfg(windows)]
+fn up_interfaces() -> Option<Vec<String>> {
+  use winapi::shared::{
+    ifdef::IfOperStatusUp,
+    winerror::{ERROR_BUFFER_OVERFLOW, NO_ERROR},
+    ws2def::AF_UNSPEC,
+  };
+  use winapi::um::{iphlpapi::GetAdaptersAddresses, iptypes::IP_ADAPTER_ADDRESSES};
+
+  let mut size: u32 = 16 * 1024;
+
+  let buffer = loop {
+    // keep the adapters aligned
+    let mut buffer = vec![0u64; size as usize / 8 + 1];
+    let first = buffer.as_mut_ptr() as *mut IP_ADAPTER_ADDRESSES;
+    let ret =
+      unsafe { GetAdaptersAddresses(AF_UNSPEC as u32, 0, std::ptr::null_mut(), first, &mut size) };
+
+    match ret {
+      NO_ERROR => break buffer,
+      ERROR_BUFFER_OVERFLOW => continue,
+      _ => return None,
+    }
+  };
+
+  let mut names = vec![];
+  let mut cursor = buffer.as_ptr() as *const IP_ADAPTER_ADDRESSES;
+
+  while !cursor.is_null() {
+    let adapter = unsafe { &*cursor };
+
+    if adapter.OperStatus == IfOperStatusUp && !adapter.FriendlyName.is_null() {
+      let name = unsafe {
+        let len = (0..)
+          .take_while(|&i| *adapter.FriendlyName.add(i) != 0)
+          .count();
+        std::slice::from_raw_parts(adapter.FriendlyName, len)
+      };
+      names.push(String::from_utf16_lossy(name));
+    }
+    cursor = adapter.Next;
+  }
+
+  Some(names)
+}
+
+#[cfg(not(any(unix, windows)))]
+fn up_interfaces() -> Option<Vec<String>> {
+  None
+}
+
+fn to_cidr(ip: Ipv4Addr, netmask: Ipv4Addr) -> String {
+  let mask = u32::from(netmask);
+  let net = Ipv4Addr::from(u32::from(ip) & mask);
+  format!("{net}/{}", mask.count_ones())
+}
+
+/// parse the subscription url from the deep link
+/// like `clash://install-config?url=xxx`
+pub fn parse_import_link(link: &str) -> Option<String> {
+  let link = Url::parse(link).ok()?;
+
+  if link.scheme() != "clash" || link.host_str() != Some("install-config") {
+    return None;
+  }
+
+  link
+    .query_pairs()
+    .find(|(key, _)| key == "url")
+    .map(|(_, url)| url.to_string())
+    .filter(|url| !url.is_empty())
+}
+
+/// decode the content of the first qr code in the image
+pub fn decode_qr_image(path: &Path) -> Result<String> {
+  let image = match image::open(path) {
+    Ok(image) => image.to_luma8(),
+    Err(err) => bail!("failed to read the image, {err}"),
+  };
+
+  let mut image = rqrr::PreparedImage::prepare(image);
+  let grids = image.detect_grids();
+
+  if grids.is_empty() {
+    bail!("no qr code found in the image");
+  }
+
+  for grid in grids.iter() {
+    if let Ok((_, content)) = grid.decode() {
+      let content = content.trim();
+
+      if !content.is_empty() {
+        return Ok(content.into());
+      }
+    }
+  }
+
+  bail!("failed to decode the qr code")
+}
+
 #[macro_export]
 macro_rules! log_if_err {
   ($result: expr) => {
@@ -92,3 +321,25 @@ fn test_parse_value() {
   assert_eq!(parse_str::<usize>(test_1, "expire1="), None);
   assert_eq!(parse_str::<usize>(test_2, "attachment="), None);
 }
+
+#[test]
+fn test_parse_import_link() {
+  let link = "clash://install-config?url=https%3A%2F%2Fexample.com%2Fsub%3Ftoken%3D1&name=test";
+
+  assert_eq!(
+    parse_import_link(link),
+    Some("https://example.com/sub?token=1".into())
+  );
+  assert_eq!(parse_import_link("clash://install-config?name=test"), None);
+  assert_eq!(parse_import_link("https://example.com/?url=xxx"), None);
+  assert_eq!(parse_import_link("--silent"), None);
+}
+
+#[test]
+fn test_to_cidr() {
+  let cidr = |ip: &str, mask: &str| to_cidr(ip.parse().unwrap(), mask.parse().unwrap());
+
+  assert_eq!(cidr("192.168.1.23", "255.255.255.0"), "192.168.1.0/24");
+  assert_eq!(cidr("10.8.3.4", "255.0.0.0"), "10.0.0.0/8");
+  assert_eq!(cidr("172.20.5.6", "255.255.240.0"), "172.20.0.0/20");
+}
@@ -0,0 +1,112 @@
+use super::resolve;
+use crate::{
+  cmds,
+  core::{Clash, VergeConfig},
+  log_if_err, states,
+};
+use anyhow::{anyhow, bail, Result};
+use std::collections::HashMap;
+use tauri::{AppHandle, GlobalShortcutManager, Manager};
+
+/// the actions which could be bound to the hotkeys
+const HOTKEY_ACTIONS: [&str; 4] = [
+  "toggle_system_proxy",
+  "toggle_tun_mode",
+  "switch_mode",
+  "toggle_window",
+];
+
+/// register the global hotkeys
+/// the hotkeys map is `action -> accelerator`
+/// all the old hotkeys would be unregistered
+pub fn register(app_handle: &AppHandle, hotkeys: &HashMap<String, String>) -> Result<()> {
+  // validate all of them before registering
+  let mut used: Vec<&String> = vec![];
+
+  for (action, accelerator) in hotkeys.iter() {
+    if !HOTKEY_ACTIONS.contains(&action.as_str()) {
+      bail!("invalid hotkey action \"{action}\"");
+    }
+    if accelerator.is_empty() {
+      continue;
+    }
+    if used.contains(&accelerator) {
+      bail!("the hotkey \"{accelerator}\" is bound to multiple actions");
+    }
+    used.push(accelerator);
+  }
+
+  let mut manager = app_handle.global_shortcut_manager();
+  manager.unregister_all()?;
+
+  for (action, accelerator) in hotkeys.iter() {
+    if accelerator.is_empty() {
+      continue;
+    }
+
+    let app_handle = app_handle.clone();
+    let action = action.clone();
+
+    manager
+      .register(accelerator, move || exec_action(&app_handle, &action))
+      .map_err(|err| anyhow!("failed to register the hotkey \"{accelerator}\" for {err}"))?;
+  }
+
+  Ok(())
+}
+
+/// run the action
+/// same as the commands or the tray do
+fn exec_action(app_handle: &AppHandle, action: &str) {
+  match action {
+    "toggle_system_proxy" | "toggle_tun_mode" => {
+      let mut patch = VergeConfig::default();
+
+      {
+        let verge_state = app_handle.state::<states::VergeState>();
+        let verge = verge_state.0.lock().unwrap();
+        let config = &verge.config;
+
+        match action {
+          "toggle_system_proxy" => {
+            patch.enable_system_proxy = Some(!config.enable_system_proxy.unwrap_or(false));
+          }
+          _ => patch.enable_tun_mode = Some(!config.enable_tun_mode.unwrap_or(false)),
+        };
+      }
+
+      log_if_err!(cmds::patch_verge_config(
+        patch,
+        app_handle.clone(),
+        app_handle.state::<states::ClashState>(),
+        app_handle.state::<states::VergeState>(),
+        app_handle.state::<states::ProfilesState>(),
+      ));
+
+      if let Some(window) = app_handle.get_window("main") {
+        log_if_err!(window.emit("verge://refresh-verge-config", "yes"));
+      }
+    }
+    "switch_mode" => {
+      let mode = match Clash::read_mode().as_str() {
+        "rule" => "global",
+        "global" => "direct",
+        _ => "rule",
+      };
+      resolve::resolve_mode(app_handle, mode);
+    }
+    "toggle_window" => {
+      if let Some(window) = app_handle.get_window("main") {
+        match window.is_visible() {
+          Ok(true) => log_if_err!(window.hide()),
+          _ => {
+            log_if_err!(window.unminimize());
+            log_if_err!(window.show());
+            log_if_err!(window.set_focus());
+          }
+        }
+      }
+    }
+    _ => {}
+  }
+}
@@ -0,0 +1,27 @@
+use crate::core::{Clash, VergeConfig};
+use anyhow::Result;
+use reqwest::{ClientBuilder, Proxy};
+
+/// the http client builder for the outbound requests of the app
+/// the proxied requests go through the `app_proxy` or the clash core
+pub fn client_builder(with_proxy: bool) -> Result<ClientBuilder> {
+  let mut builder = ClientBuilder::new().no_proxy();
+
+  // fallback to the direct request if the proxy is unavailable
+  if with_proxy {
+    match proxy_url() {
+      Some(url) => builder = builder.proxy(Proxy::all(url)?),
+      None => log::warn!("failed to get the proxy, send the request directly"),
+    }
+  }
+
+  Ok(builder)
+}
+
+/// the `app_proxy` takes precedence over the clash port
+fn proxy_url() -> Option<String> {
+  match VergeConfig::new().app_proxy {
+    Some(proxy) if !proxy.trim().is_empty() => Some(proxy.trim().to_string()),
+    _ => Clash::read_port().map(|port| format!("http://127.0.0.1:{port}")),
+  }
+}
@@ -1,28 +1,50 @@
+use crate::core::VergeConfig;
 use crate::utils::{dirs, tmpl};
+use anyhow::{bail, Result};
 use chrono::Local;
 use log::LevelFilter;
 use log4rs::append::console::ConsoleAppender;
-use log4rs::append::file::FileAppender;
+use log4rs::append::rolling_file::policy::compound::{
+  roll::fixed_window::FixedWindowRoller, trigger::size::SizeTrigger, CompoundPolicy,
+};
+use log4rs::append::rolling_file::RollingFileAppender;
 use log4rs::config::{Appender, Config, Root};
 use log4rs::encode::pattern::PatternEncoder;
 use std::fs;
 use std::io::Write;
 use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 use tauri::PackageInfo;
 
+/// roll the log file when it is larger than 10MB
+const LOG_FILE_SIZE: u64 = 10 * 1024 * 1024;
+/// keep 5 rolled files for each instance
+const LOG_FILE_COUNT: u32 = 5;
+/// keep the log files in 7 days by default
+const MAX_LOG_DAYS: u64 = 7;
+
 /// initialize this instance's log file
+/// the level is controlled by the `log::set_max_level`
+/// the file would be rolled by size
 fn init_log(log_dir: &PathBuf) {
   let local_time = Local::now().format("%Y-%m-%d-%H%M%S").to_string();
   let log_file = format!("{}.log", local_time);
   let log_file = log_dir.join(log_file);
+  let roll_file = format!("{}.{{}}.log", local_time);
+  let roll_file = log_dir.join(roll_file);
+
+  let roller = FixedWindowRoller::builder()
+    .build(roll_file.to_str().unwrap(), LOG_FILE_COUNT)
+    .unwrap();
+  let policy = CompoundPolicy::new(Box::new(SizeTrigger::new(LOG_FILE_SIZE)), Box::new(roller));
 
   let time_format = "{d(%Y-%m-%d %H:%M:%S)} - {m}{n}";
   let stdout = ConsoleAppender::builder()
     .encoder(Box::new(PatternEncoder::new(time_format)))
     .build();
-  let tofile = FileAppender::builder()
+  let tofile = RollingFileAppender::builder()
     .encoder(Box::new(PatternEncoder::new(time_format)))
-    .build(log_file)
+    .build(log_file, Box::new(policy))
     .unwrap();
 
   let config = Config::builder()
@@ -31,11 +53,65 @@ fn init_log(log_dir: &PathBuf) {
     .build(
       Root::builder()
         .appenders(["stdout", "file"])
-        .build(LevelFilter::Info),
+        .build(LevelFilter::Trace),
     )
     .unwrap();
 
   log4rs::init_config(config).unwrap();
+
+  let level = VergeConfig::new().log_level;
+  if let Err(err) = set_log_level(level.as_ref()) {
+    log::set_max_level(LevelFilter::Info);
+    log::error!("{err}");
+  }
+}
+
+/// change the log level at runtime
+/// default is `info`
+pub fn set_log_level(level: Option<&String>) -> Result<()> {
+  let level = match level.map(|l| l.to_ascii_lowercase()).as_deref() {
+    None => LevelFilter::Info,
+    Some("error") => LevelFilter::Error,
+    Some("warn") => LevelFilter::Warn,
+    Some("info") => LevelFilter::Info,
+    Some("debug") => LevelFilter::Debug,
+    Some("trace") => LevelFilter::Trace,
+    Some(level) => bail!("invalid log level \"{level}\""),
+  };
+
+  log::set_max_level(level);
+  Ok(())
+}
+
+/// delete the log files older than the retention days
+fn clean_logs(log_dir: &PathBuf) -> Result<()> {
+  let days = VergeConfig::new().max_log_days.filter(|days| *days > 0);
+  let days = days.unwrap_or(MAX_LOG_DAYS);
+
+  // keep all the logs if the days are out of the range
+  let expired = days
+    .checked_mul(24 * 60 * 60)
+    .and_then(|secs| SystemTime::now().checked_sub(Duration::from_secs(secs)));
+  let expired = match expired {
+    Some(expired) => expired,
+    None => return Ok(()),
+  };
+
+  for entry in fs::read_dir(log_dir)? {
+    let path = entry?.path();
+
+    if !path.is_file() || path.extension().map_or(true, |ext| ext != "log") {
+      continue;
+    }
+
+    let modified = fs::metadata(&path)?.modified()?;
+    if modified < expired {
+      log::info!("remove the expired log file \"{}\"", path.display());
+      fs::remove_file(&path)?;
+    }
+  }
+
+  Ok(())
 }
 
 /// Initialize all the files from resources
@@ -77,6 +153,9 @@ pub fn init_app(package_info: &PackageInfo) {
   }
 
   init_log(&log_dir);
+  if let Err(err) = clean_logs(&log_dir) {
+    log::error!("failed to clean the logs for {err}");
+  }
   if let Err(err) = init_config(&app_dir) {
     log::error!("{err}");
   }
@@ -1,8 +1,14 @@
+pub mod backup;
 pub mod config;
 pub mod dirs;
 pub mod help;
+pub mod hotkey;
+pub mod http;
 pub mod init;
 pub mod resolve;
+pub mod scheme;
 pub mod server;
 pub mod sysopt;
 pub mod tmpl;
+pub mod tray;
+pub mod window;
@@ -1,6 +1,23 @@
-use super::{init, server};
-use crate::{core::Profiles, log_if_err, states};
-use tauri::{App, AppHandle, Manager};
+use super::{
+  help, hotkey, init, scheme, server, tray,
+  window::{self, WindowState},
+};
+use crate::{
+  cmds,
+  core::{clash_api, Profiles},
+  log_if_err, states,
+};
+use serde_yaml::{Mapping, Value};
+use std::{collections::HashMap, time::Duration};
+use tauri::{App, AppHandle, Manager, WindowEvent};
+use tokio::{sync::oneshot, time::sleep};
+
+/// the times to check whether the core is ready
+const CORE_READY_RETRIES: usize = 20;
+
+/// the interval to check the os theme in the `system` mode
+/// the current tauri runtime does not report the theme changes
+const THEME_CHECK_INTERVAL: Duration = Duration::from_secs(3);
 
 /// handle something when start app
 pub fn resolve_setup(app: &App) {
@@ -21,34 +38,186 @@ pub fn resolve_setup(app: &App) {
   let mut verge = verge_state.0.lock().unwrap();
   let mut profiles = profiles_state.0.lock().unwrap();
 
-  log_if_err!(clash.run_sidecar());
+  // the window is used to recover the crashed sidecar
+  clash.set_window(app.get_window("main"));
+  if let Err(err) = clash.run_sidecar() {
+    log::error!("{err}");
+    clash.launch_error = Some(err.to_string());
+  }
 
   *profiles = Profiles::read_file();
+  log_if_err!(profiles.ensure_current());
 
-  clash.set_window(app.get_window("main"));
   log_if_err!(clash.activate(&profiles, true));
 
-  verge.init_sysproxy(clash.info.port.clone());
-  // enable tun mode
-  if verge.config.enable_tun_mode.clone().unwrap_or(false)
-    && verge.cur_sysproxy.is_some()
-    && verge.cur_sysproxy.as_ref().unwrap().enable
-  {
-    log::info!("enable tun mode");
-    clash.tun_mode(true).unwrap();
-  }
+  tray::update_menu(&app.handle(), &profiles);
+
+  tray::update_icon(&app.handle(), clash.sidecar.is_some(), Some(&verge));
 
   log_if_err!(verge.init_launch());
+
+  let hotkeys = verge.config.hotkeys.clone().unwrap_or_default();
+  log_if_err!(hotkey::register(&app.handle(), &hotkeys));
+
+  resolve_sysproxy(app.handle());
+  resolve_auto_update(app.handle());
+  resolve_traffic(app.handle());
+  resolve_theme(app.handle(), verge.config.is_dark());
+
+  // refresh the handler of the moved exe
+  if verge.config.register_scheme.unwrap_or(false) {
+    log_if_err!(scheme::register_scheme());
+  }
+
+  // launched by the deep link
+  if let Some(url) = std::env::args().find_map(|arg| help::parse_import_link(&arg)) {
+    resolve_import(app.handle(), url);
+  }
+}
+
+/// set the system proxy after the core answers on its port
+/// otherwise the traffic is black-holed until the core is ready
+fn resolve_sysproxy(app_handle: AppHandle) {
+  tauri::async_runtime::spawn(async move {
+    let mut ready = false;
+
+    for _ in 0..CORE_READY_RETRIES {
+      // the core may be restarted during the retries
+      let info = {
+        let clash_state = app_handle.state::<states::ClashState>();
+        let clash = clash_state.0.lock().unwrap();
+        clash.info.clone()
+      };
+
+      if clash_api::ping(&info).await {
+        ready = true;
+        break;
+      }
+
+      sleep(Duration::from_millis(500)).await;
+    }
+
+    if !ready {
+      log::error!("the clash core is not ready, skip setting the system proxy");
+
+      if let Some(window) = app_handle.get_window("main") {
+        let err = "the clash core is not ready, the system proxy is not set";
+        log_if_err!(window.emit("verge://notice-error", err));
+      }
+      return;
+    }
+
+    let clash_state = app_handle.state::<states::ClashState>();
+    let verge_state = app_handle.state::<states::VergeState>();
+    let clash = clash_state.0.lock().unwrap();
+    let mut verge = verge_state.0.lock().unwrap();
+
+    // the tun mode is kept in `config.yaml` which the core is launched with
+    // and the system proxy is skipped by `sysproxy_enabled` in tun mode
+    verge.init_sysproxy(clash.info.port.clone());
+    tray::update_icon(&app_handle, clash.sidecar.is_some(), Some(&verge));
+  });
+}
+
+/// keep the profile url of the deep link
+/// then show the window and ask the user to confirm
+/// the window takes it by `take_pending_import` even if it is not loaded yet
+pub fn resolve_import(app_handle: AppHandle, url: String) {
+  log::info!("receive the profile url \"{url}\"");
+
+  let import_state = app_handle.state::<states::ImportState>();
+  *import_state.0.lock().unwrap() = Some(url);
+
+  if let Some(window) = app_handle.get_window("main") {
+    log_if_err!(window.show());
+    log_if_err!(window.set_focus());
+    log_if_err!(window.emit("verge://import-confirm", "yes"));
+  }
+}
+
+/// change the clash mode
+/// same as the `patch_clash_config`
+/// then reactivate to apply the mode to the running core
+pub fn resolve_mode(app_handle: &AppHandle, mode: &str) {
+  let clash_state = app_handle.state::<states::ClashState>();
+  let verge_state = app_handle.state::<states::VergeState>();
+  let profiles_state = app_handle.state::<states::ProfilesState>();
+  let mut clash = clash_state.0.lock().unwrap();
+  let mut verge = verge_state.0.lock().unwrap();
+  let mut profiles = profiles_state.0.lock().unwrap();
+
+  let mut patch = Mapping::new();
+  patch.insert(Value::from("mode"), Value::from(mode));
+
+  // the mode is locked by the kill switch
+  if let Err(err) = clash.patch_config(patch, &mut verge, &mut profiles) {
+    log::error!("{err}");
+    return;
+  }
+  log_if_err!(clash.activate(&profiles, false));
+
+  tray::update_mode(app_handle, mode);
 }
 
 /// reset system proxy
+/// unless the `reset_proxy_on_exit` is false
 pub fn resolve_reset(app_handle: &AppHandle) {
   let verge_state = app_handle.state::<states::VergeState>();
   let mut verge = verge_state.0.lock().unwrap();
 
+  // the guard file is kept to restore the original proxy on the next start
+  if !verge.config.reset_proxy_on_exit.unwrap_or(true) {
+    log::warn!("keep the system proxy after quitting");
+    return;
+  }
+
   verge.reset_sysproxy();
 }
 
+/// update the remote profiles in the background
+/// by their `update_interval` option
+fn resolve_auto_update(app_handle: AppHandle) {
+  tauri::async_runtime::spawn(async move {
+    // record the last attempt time
+    // avoid retrying the failed one every minute
+    let mut attempts: HashMap<String, usize> = HashMap::new();
+
+    loop {
+      sleep(Duration::from_secs(60)).await;
+
+      let list = {
+        let profiles_state = app_handle.state::<states::ProfilesState>();
+        let profiles = profiles_state.0.lock().unwrap();
+        profiles.get_update_list()
+      };
+
+      let now = help::get_now();
+
+      for uid in list.into_iter() {
+        if let Some(last) = attempts.get(&uid) {
+          if *last + 60 * 60 > now {
+            continue;
+          }
+        }
+        attempts.insert(uid.clone(), now);
+
+        log::info!("auto update profile \"{uid}\"");
+
+        let clash_state = app_handle.state::<states::ClashState>();
+        let profiles_state = app_handle.state::<states::ProfilesState>();
+        let update_state = app_handle.state::<states::UpdateState>();
+
+        if cmds::update_profile(uid.clone(), None, clash_state, profiles_state, update_state)
+          .await
+          .is_ok()
+        {
+          attempts.remove(&uid);
+        }
+      }
+    }
+  });
+}
+
 /// customize the window theme
 fn resolve_window(app: &App) {
   let window = app.get_window("main").unwrap();
@@ -56,13 +225,14 @@ fn resolve_window(app: &App) {
   #[cfg(target_os = "windows")]
   {
     use window_shadows::set_shadow;
-    use window_vibrancy::apply_blur;
 
     window.set_decorations(false).unwrap();
     set_shadow(&window, true).unwrap();
-    apply_blur(&window, None).unwrap();
   }
 
+  // the initial theme before the webview is loaded
+  window::apply_effect(&window, &crate::core::VergeConfig::new());
+
   #[cfg(target_os = "macos")]
   {
     use tauri::LogicalSize;
@@ -78,4 +248,141 @@ fn resolve_window(app: &App) {
     // #[allow(deprecated)]
     // window.apply_vibrancy(MacOSVibrancy::AppearanceBased);
   }
+
+  // restore the last window state
+  if let Some(state) = WindowState::read_file() {
+    log_if_err!(state.apply(&window));
+  }
+
+  let win = window.clone();
+  window.on_window_event(move |event| match event {
+    WindowEvent::Moved(_) | WindowEvent::Resized(_) => {
+      window::save_window_state_later(win.clone());
+    }
+    _ => {}
+  });
+
+  // the window is hidden by default
+  // keep it in the tray when silent start
+  let silent_start = {
+    let verge_state = app.state::<states::VergeState>();
+    let verge = verge_state.0.lock().unwrap();
+    verge.config.silent_start.unwrap_or(false)
+  };
+
+  if !silent_start {
+    window.show().unwrap();
+  }
+}
+
+/// forward the clash traffic to the window
+/// reconnect if the core restarts
+/// stop when the window is closed
+fn resolve_traffic(app_handle: AppHandle) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      let window = match app_handle.get_window("main") {
+        Some(window) => window,
+        None => break,
+      };
+
+      // the port may be changed after restart
+      let info = {
+        let clash_state = app_handle.state::<states::ClashState>();
+        let clash = clash_state.0.lock().unwrap();
+        clash.info.clone()
+      };
+
+      let result = clash_api::stream_traffic(&info, |traffic| {
+        log_if_err!(window.emit("verge://traffic", traffic));
+      })
+      .await;
+
+      if let Err(err) = result {
+        log::debug!("the traffic stream is closed for {err}");
+      }
+
+      sleep(Duration::from_secs(1)).await;
+    }
+  });
+}
+
+/// follow the os theme in the `system` mode
+/// retint the window effect and the tray icon, then notify the window
+fn resolve_theme(app_handle: AppHandle, mut dark: bool) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      sleep(THEME_CHECK_INTERVAL).await;
+
+      let system = {
+        let verge_state = app_handle.state::<states::VergeState>();
+        let verge = verge_state.0.lock().unwrap();
+        verge.config.theme_mode.as_deref() == Some("system")
+      };
+
+      // the other modes are applied by the `patch_verge_config`
+      if !system || help::system_dark() == dark {
+        continue;
+      }
+      dark = !dark;
+
+      let running = {
+        let clash_state = app_handle.state::<states::ClashState>();
+        let clash = clash_state.0.lock().unwrap();
+        clash.sidecar.is_some()
+      };
+
+      let verge_state = app_handle.state::<states::VergeState>();
+      let verge = verge_state.0.lock().unwrap();
+      tray::update_icon(&app_handle, running, Some(&verge));
+
+      if let Some(window) = app_handle.get_window("main") {
+        window::apply_effect(&window, &verge.config);
+        log_if_err!(window.emit("verge://theme-changed", dark));
+      }
+    }
+  });
+}
+
+/// forward the clash logs to the window
+/// reconnect if the core restarts
+/// stop when the receiver is notified or the sender is dropped
+pub fn resolve_logs(
+  app_handle: AppHandle,
+  level: Option<String>,
+  mut receiver: oneshot::Receiver<()>,
+) {
+  tauri::async_runtime::spawn(async move {
+    loop {
+      let window = match app_handle.get_window("main") {
+        Some(window) => window,
+        None => break,
+      };
+
+      // the port may be changed after restart
+      let info = {
+        let clash_state = app_handle.state::<states::ClashState>();
+        let clash = clash_state.0.lock().unwrap();
+        clash.info.clone()
+      };
+
+      let stream = clash_api::stream_logs(&info, level.as_deref(), |log| {
+        log_if_err!(window.emit("verge://log", log));
+      });
+
+      tokio::select! {
+        result = stream => {
+          if let Err(err) = result {
+            log::debug!("the log stream is closed for {err}");
+          }
+        }
+        _ = &mut receiver => break,
+      }
+
+      tokio::select! {
+        _ = sleep(Duration::from_secs(1)) => {}
+        _ = &mut receiver => break,
+      }
+    }
+  });
 }
@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+/// the url scheme used by the subscription sites
+/// like `clash://install-config?url=xxx`
+#[allow(dead_code)]
+static SCHEME: &str = "clash";
+
+/// register the app as the handler of the `clash://` links
+/// the link would be passed to the app by argv
+#[cfg(target_os = "windows")]
+pub fn register_scheme() -> Result<()> {
+  use tauri::utils::platform::current_exe;
+  use winreg::enums::*;
+  use winreg::RegKey;
+
+  let exe = current_exe()?;
+  let hkcu = RegKey::predef(HKEY_CURRENT_USER);
+
+  let (key, _) = hkcu.create_subkey(format!("Software\\Classes\\{SCHEME}"))?;
+  key.set_value("", &"URL:Clash Protocol")?;
+  key.set_value("URL Protocol", &"")?;
+
+  let (command, _) = key.create_subkey("shell\\open\\command")?;
+  command.set_value("", &format!("\"{}\" \"%1\"", exe.display()))?;
+
+  Ok(())
+}
+
+/// register the desktop entry for the `x-scheme-handler`
+#[cfg(target_os = "linux")]
+pub fn register_scheme() -> Result<()> {
+  use anyhow::Context;
+  use std::{fs, process::Command};
+  use tauri::{api::path::home_dir, utils::platform::current_exe};
+
+  let exe = current_exe()?;
+  let name = "clash-verge-url-handler.desktop";
+
+  let dir = home_dir()
+    .context("failed to get the home dir")?
+    .join(".local/share/applications");
+  fs::create_dir_all(&dir)?;
+
+  let entry = format!(
+    "[Desktop Entry]\nType=Application\nName=Clash Verge\nExec=\"{}\" %u\nNoDisplay=true\nMimeType=x-scheme-handler/{SCHEME};\n",
+    exe.display()
+  );
+  fs::write(dir.join(name), entry)?;
+
+  let mime = format!("x-scheme-handler/{SCHEME}");
+  Command::new("xdg-mime")
+    .args(["default", name, mime.as_str()])
+    .status()?;
+
+  Ok(())
+}
+
+/// the link is sent by the apple event instead of argv on macos,
+/// which is not exposed by the current tauri runtime
+#[cfg(target_os = "macos")]
+pub fn register_scheme() -> Result<()> {
+  anyhow::bail!("the clash:// links are not supported on macos")
+}
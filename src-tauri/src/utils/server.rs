@@ -1,41 +1,191 @@
 extern crate warp;
 
+use super::{dirs, help, resolve, sysopt::SysProxyConfig};
+use crate::core::{Clash, VergeConfig};
+use once_cell::sync::OnceCell;
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
 use port_scanner::local_port_available;
+use std::{collections::HashMap, time::Duration};
 use tauri::{AppHandle, Manager};
-use warp::Filter;
+use warp::{http::StatusCode, Filter};
 
 #[cfg(not(feature = "verge-dev"))]
 const SERVER_PORT: u16 = 33333;
 #[cfg(feature = "verge-dev")]
 const SERVER_PORT: u16 = 11233;
 
+/// override the port of the embed server
+const SERVER_PORT_ENV: &str = "CLASH_VERGE_SERVER_PORT";
+
+/// the reply of the ping to identify the running instance
+const PING_REPLY: &str = "clash-verge";
+
+/// the header of the token only known by the local instances
+const TOKEN_HEADER: &str = "x-verge-token";
+
+/// the dead instance should not block the launch for long
+const PING_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// the port of the embed server
+/// read from the env or the verge config once
+/// the server keeps listening on it until the next launch
+pub fn server_port() -> u16 {
+  static PORT: OnceCell<u16> = OnceCell::new();
+
+  *PORT.get_or_init(|| {
+    std::env::var(SERVER_PORT_ENV)
+      .ok()
+      .and_then(|port| port.parse::<u16>().ok())
+      .or(VergeConfig::new().server_port)
+      .unwrap_or(SERVER_PORT)
+  })
+}
+
 /// check whether there is already exists
+/// forward the deep link to the running one
+/// launch anyway if the port is occupied by other programs
+/// or the dead instance which does not reply the ping
+/// never launch the second one if the running one replies
 pub fn check_singleton() -> Result<(), ()> {
-  if !local_port_available(SERVER_PORT) {
-    tauri::async_runtime::block_on(async {
-      let url = format!("http://127.0.0.1:{}/commands/visible", SERVER_PORT);
-      reqwest::get(url).await.unwrap();
-      Err(())
-    })
-  } else {
-    Ok(())
+  if !VergeConfig::new().enable_singleton.unwrap_or(true) {
+    return Ok(());
+  }
+
+  let port = server_port();
+
+  if local_port_available(port) {
+    return Ok(());
   }
+
+  let link = std::env::args().find_map(|arg| help::parse_import_link(&arg));
+
+  tauri::async_runtime::block_on(async {
+    let client = match reqwest::ClientBuilder::new()
+      .no_proxy()
+      .timeout(PING_TIMEOUT)
+      .build()
+    {
+      Ok(client) => client,
+      Err(_) => return Ok(()),
+    };
+
+    let ping = client
+      .get(format!("http://127.0.0.1:{port}/commands/ping"))
+      .send()
+      .await;
+
+    let alive = match ping {
+      Ok(resp) if resp.status().is_success() => {
+        resp.text().await.ok().as_deref() == Some(PING_REPLY)
+      }
+      _ => false,
+    };
+
+    if !alive {
+      log::warn!("the port {port} is occupied by other programs or the dead instance");
+      return Ok(());
+    }
+
+    let request = match link {
+      Some(url) => {
+        let url = utf8_percent_encode(&url, NON_ALPHANUMERIC);
+        let token = std::fs::read_to_string(dirs::server_token_path()).unwrap_or_default();
+
+        client
+          .post(format!("http://127.0.0.1:{port}/commands/import?url={url}"))
+          .header(TOKEN_HEADER, token.trim())
+      }
+      None => client.get(format!("http://127.0.0.1:{port}/commands/visible")),
+    };
+
+    // the running one may reject the forward by the token or the url
+    // or be an old version without the import
+    match request.send().await {
+      Ok(resp) if resp.status().is_success() => {}
+      Ok(resp) => log::warn!(
+        "failed to forward to the running instance for status \"{}\"",
+        resp.status()
+      ),
+      Err(err) => log::warn!("failed to forward to the running instance for {err}"),
+    }
+
+    Err(())
+  })
 }
 
-/// The embed server only be used to implement singleton process
-/// maybe it can be used as pac server later
+/// the pac url served by the embed server
+pub fn pac_url() -> String {
+  format!("http://127.0.0.1:{}/pac", server_port())
+}
+
+/// The embed server is used to implement singleton process
+/// and serve the pac script for the `pac` proxy mode
+///
+/// it only listens on the localhost without authentication,
+/// so any local process could show the window or read the pac
+/// the import requires the per-launch token saved in the app dir
 pub fn embed_server(app: &AppHandle) {
   let window = app.get_window("main").unwrap();
+  let app_handle = app.clone();
+  let port = server_port();
+
+  let token = nanoid::nanoid!(32);
+  if let Err(err) = std::fs::write(dirs::server_token_path(), &token) {
+    log::error!("failed to save the server token for {err}");
+  }
 
   tauri::async_runtime::spawn(async move {
+    let ping = warp::path!("commands" / "ping").map(|| PING_REPLY);
+
     let commands = warp::path!("commands" / "visible").map(move || {
       window.show().unwrap();
       window.set_focus().unwrap();
       return format!("ok");
     });
 
-    warp::serve(commands)
-      .bind(([127, 0, 0, 1], SERVER_PORT))
-      .await;
+    // the deep link forwarded by the second instance
+    // reject the cross-site requests from the browsers
+    let import = warp::path!("commands" / "import")
+      .and(warp::post())
+      .and(warp::header::optional::<String>("origin"))
+      .and(warp::header::optional::<String>(TOKEN_HEADER))
+      .and(warp::query::<HashMap<String, String>>())
+      .map(
+        move |origin: Option<String>, req_token: Option<String>, query: HashMap<String, String>| {
+          if origin.is_some() || req_token.as_deref() != Some(token.as_str()) {
+            log::warn!("reject the unauthorized import request");
+            return warp::reply::with_status(format!("forbidden"), StatusCode::FORBIDDEN);
+          }
+
+          match query.get("url") {
+            Some(url) => {
+              resolve::resolve_import(app_handle.clone(), url.clone());
+              warp::reply::with_status(format!("ok"), StatusCode::OK)
+            }
+            None => {
+              warp::reply::with_status(format!("failed to get the url"), StatusCode::BAD_REQUEST)
+            }
+          }
+        },
+      );
+
+    // read the latest port and bypass for each request
+    let pac = warp::path!("pac").map(|| {
+      let port = Clash::read_port().unwrap_or("7890".into());
+      let bypass = VergeConfig::new().system_proxy_bypass;
+
+      warp::reply::with_header(
+        SysProxyConfig::gen_pac(&port, bypass),
+        "Content-Type",
+        "application/x-ns-proxy-autoconfig",
+      )
+    });
+
+    match warp::serve(ping.or(commands).or(import).or(pac))
+      .try_bind_ephemeral(([127, 0, 0, 1], port))
+    {
+      Ok((_, server)) => server.await,
+      Err(err) => log::error!("failed to launch the embed server on port {port} for {err}"),
+    }
   });
 }
@@ -1,5 +1,6 @@
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
+use std::net::Ipv4Addr;
 
 #[cfg(target_os = "windows")]
 static DEFAULT_BYPASS: &str = "localhost;127.*;10.*;172.16.*;172.17.*;172.18.*;172.19.*;172.20.*;172.21.*;172.22.*;172.23.*;172.24.*;172.25.*;172.26.*;172.27.*;172.28.*;172.29.*;172.30.*;172.31.*;192.168.*;<local>";
@@ -7,7 +8,13 @@ static DEFAULT_BYPASS: &str = "localhost;127.*;10.*;172.16.*;172.17.*;172.18.*;1
 static DEFAULT_BYPASS: &str = "localhost,127.0.0.1/8,::1";
 #[cfg(target_os = "macos")]
 static DEFAULT_BYPASS: &str =
-  "192.168.0.0/16\n10.0.0.0/8\n172.16.0.0/12\n127.0.0.1\nlocalhost\n*.local\ntimestamp.apple.com\n";
+  "192.168.0.0/16,10.0.0.0/8,172.16.0.0/12,127.0.0.1,localhost,*.local,timestamp.apple.com";
+
+/// the separator of the bypass list used by the os
+#[cfg(target_os = "windows")]
+static BYPASS_SEP: &str = ";";
+#[cfg(not(target_os = "windows"))]
+static BYPASS_SEP: &str = ",";
 #[cfg(target_os = "macos")]
 static MACOS_SERVICE: &str = "Wi-Fi";
 
@@ -16,6 +23,27 @@ pub struct SysProxyConfig {
   pub enable: bool,
   pub server: String,
   pub bypass: String,
+
+  /// use the auto config url instead of the server
+  /// only works when `enable` is true
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub pac_url: Option<String>,
+
+  /// `all` `http` or `socks`, default is `all`
+  /// the protocols not included would be left untouched
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub protocol: Option<String>,
+}
+
+/// the live system proxy
+/// and whether it is changed by other apps
+#[derive(Debug, Serialize, Clone)]
+pub struct SysProxyStatus {
+  #[serde(flatten)]
+  pub sys: SysProxyConfig,
+
+  /// the system proxy set by the app is overridden
+  pub tampered: bool,
 }
 
 impl Default for SysProxyConfig {
@@ -24,6 +52,8 @@ impl Default for SysProxyConfig {
       enable: false,
       server: String::from(""),
       bypass: String::from(""),
+      pac_url: None,
+      protocol: None,
     }
   }
 }
@@ -33,9 +63,104 @@ impl SysProxyConfig {
     SysProxyConfig {
       enable,
       server: format!("127.0.0.1:{}", port),
-      bypass: bypass.unwrap_or(DEFAULT_BYPASS.into()),
+      bypass: SysProxyConfig::merge_bypass(bypass),
+      pac_url: None,
+      protocol: None,
+    }
+  }
+
+  /// whether to set the (http, socks) proxy
+  pub fn protocols(&self) -> (bool, bool) {
+    match self.protocol.as_deref() {
+      Some("http") => (true, false),
+      Some("socks") => (false, true),
+      _ => (true, true),
+    }
+  }
+
+  /// whether the live system proxy is the one set by this config
+  /// the bypass is not compared since the os may reformat it
+  pub fn is_same(&self, sys: &SysProxyConfig) -> bool {
+    if self.enable != sys.enable {
+      return false;
+    }
+    if !self.enable {
+      return true;
+    }
+
+    match self.pac_url.as_ref() {
+      Some(pac_url) => sys.pac_url.as_ref() == Some(pac_url),
+      // the server may be prefixed with the protocol like `http=127.0.0.1:7890`
+      None => {
+        sys.pac_url.is_none()
+          && sys
+            .server
+            .split(';')
+            .filter_map(|server| server.rsplit('=').next())
+            .any(|server| server.trim() == self.server)
+      }
+    }
+  }
+
+  /// merge the default bypass of the os with the user's
+  /// the user's list could be separated by `;` `,` or newline
+  /// keep the order and remove the duplicates
+  pub fn merge_bypass(bypass: Option<String>) -> String {
+    let bypass = bypass.unwrap_or("".into());
+    let mut list: Vec<&str> = vec![];
+
+    DEFAULT_BYPASS
+      .split(|c: char| c == ';' || c == ',' || c == '\n')
+      .chain(bypass.split(|c: char| c == ';' || c == ',' || c == '\n'))
+      .map(|host| host.trim())
+      .filter(|host| !host.is_empty())
+      .for_each(|host| {
+        if !list.contains(&host) {
+          list.push(host);
+        }
+      });
+
+    list.join(BYPASS_SEP)
+  }
+
+  /// generate the pac script
+  /// the bypass list is merged with the default one as the system proxy's
+  pub fn gen_pac(port: &str, bypass: Option<String>) -> String {
+    let bypass = SysProxyConfig::merge_bypass(bypass);
+
+    let rules = bypass
+      .split(|c: char| c == ';' || c == ',' || c == '\n')
+      .map(|host| host.trim())
+      .filter(|host| !host.is_empty())
+      .map(|host| format!("  if ({}) return \"DIRECT\";\n", pac_condition(host)))
+      .collect::<String>();
+
+    format!(
+      "function FindProxyForURL(url, host) {{\n{rules}  return \"PROXY 127.0.0.1:{port}; SOCKS5 127.0.0.1:{port}; DIRECT\";\n}}\n"
+    )
+  }
+}
+
+/// transform the bypass item to the pac condition
+fn pac_condition(host: &str) -> String {
+  if host == "<local>" {
+    return "isPlainHostName(host)".into();
+  }
+
+  // cidr like `192.168.0.0/16`
+  if let Some((net, bits)) = host.split_once('/') {
+    if let (Ok(net), Ok(bits)) = (net.parse::<Ipv4Addr>(), bits.parse::<u32>()) {
+      let mask = match bits {
+        0 => 0u32,
+        1..=32 => !0u32 << (32 - bits),
+        _ => !0u32,
+      };
+      let mask = Ipv4Addr::from(mask);
+      return format!("isInNet(host, \"{net}\", \"{mask}\")");
     }
   }
+
+  format!("shExpMatch(host, \"{host}\")")
 }
 
 #[cfg(target_os = "windows")]
@@ -51,10 +176,17 @@ impl SysProxyConfig {
       KEY_READ,
     )?;
 
+    let pac_url = cur_var
+      .get_value::<String, _>("AutoConfigURL")
+      .ok()
+      .filter(|url| !url.is_empty());
+
     Ok(SysProxyConfig {
-      enable: cur_var.get_value::<u32, _>("ProxyEnable")? == 1u32,
+      enable: cur_var.get_value::<u32, _>("ProxyEnable")? == 1u32 || pac_url.is_some(),
       server: cur_var.get_value("ProxyServer")?,
       bypass: cur_var.get_value("ProxyOverride")?,
+      pac_url,
+      protocol: None,
     })
   }
 
@@ -68,12 +200,28 @@ impl SysProxyConfig {
       KEY_SET_VALUE,
     )?;
 
-    let enable: u32 = if self.enable { 1u32 } else { 0u32 };
+    let use_pac = self.enable && self.pac_url.is_some();
+    let enable: u32 = if self.enable && !use_pac { 1u32 } else { 0u32 };
+
+    // the single value could specify the protocols
+    let server = match self.protocols() {
+      (true, false) => format!("http={0};https={0}", self.server),
+      (false, true) => format!("socks={}", self.server),
+      _ => self.server.clone(),
+    };
 
     cur_var.set_value("ProxyEnable", &enable)?;
-    cur_var.set_value("ProxyServer", &self.server)?;
+    cur_var.set_value("ProxyServer", &server)?;
     cur_var.set_value("ProxyOverride", &self.bypass)?;
 
+    match use_pac {
+      true => cur_var.set_value("AutoConfigURL", self.pac_url.as_ref().unwrap())?,
+      // the value may not exist
+      false => {
+        let _ = cur_var.delete_value("AutoConfigURL");
+      }
+    };
+
     Ok(())
   }
 }
@@ -116,10 +264,17 @@ impl SysProxyConfig {
       .collect::<Vec<_>>()
       .join(",");
 
+    let pac_url = macproxy::get_auto_proxy(MACOS_SERVICE)?;
+    if pac_url.is_some() {
+      enable = true;
+    }
+
     Ok(SysProxyConfig {
       enable,
       server,
       bypass,
+      pac_url,
+      protocol: None,
     })
   }
 
@@ -127,12 +282,22 @@ impl SysProxyConfig {
   pub fn set_sys(&self) -> Result<()> {
     use std::process::Command;
 
-    let enable = self.enable;
+    let use_pac = self.enable && self.pac_url.is_some();
+    let enable = self.enable && !use_pac;
     let server = self.server.as_str();
     let bypass = self.bypass.clone();
-    macproxy::set_proxy("-setwebproxy", MACOS_SERVICE, enable, server)?;
-    macproxy::set_proxy("-setsecurewebproxy", MACOS_SERVICE, enable, server)?;
-    macproxy::set_proxy("-setsocksfirewallproxy", MACOS_SERVICE, enable, server)?;
+    let (http, socks) = self.protocols();
+
+    if http {
+      macproxy::set_proxy("-setwebproxy", MACOS_SERVICE, enable, server)?;
+      macproxy::set_proxy("-setsecurewebproxy", MACOS_SERVICE, enable, server)?;
+    }
+    if socks {
+      macproxy::set_proxy("-setsocksfirewallproxy", MACOS_SERVICE, enable, server)?;
+    }
+
+    let pac_url = self.pac_url.as_ref().filter(|_| use_pac);
+    macproxy::set_auto_proxy(MACOS_SERVICE, pac_url)?;
 
     let domains = bypass.split(",").collect::<Vec<_>>();
     Command::new("networksetup")
@@ -189,6 +354,39 @@ mod macproxy {
     Ok(())
   }
 
+  /// use networksetup
+  /// get the auto proxy url if it is enabled
+  pub(super) fn get_auto_proxy(device: &str) -> Result<Option<String>> {
+    let output = Command::new("networksetup")
+      .args(["-getautoproxyurl", device])
+      .output()?;
+
+    let stdout = std::str::from_utf8(&output.stdout)?;
+    let url = parse(stdout, "URL:");
+    let enable = parse(stdout, "Enabled:");
+
+    match enable == "Yes" && !url.is_empty() {
+      true => Ok(Some(url.into())),
+      false => Ok(None),
+    }
+  }
+
+  /// use networksetup
+  /// set the auto proxy url or turn it off
+  pub(super) fn set_auto_proxy(device: &str, url: Option<&String>) -> Result<()> {
+    if let Some(url) = url {
+      Command::new("networksetup")
+        .args(["-setautoproxyurl", device, url.as_str()])
+        .status()?;
+    }
+
+    let enable = if url.is_some() { "on" } else { "off" };
+    Command::new("networksetup")
+      .args(["-setautoproxystate", device, enable])
+      .status()?;
+    Ok(())
+  }
+
   /// parse the networksetup output
   fn parse<'a>(target: &'a str, key: &'a str) -> &'a str {
     match target.find(key) {
@@ -246,8 +444,20 @@ impl SysProxyConfig {
     let mode = Command::new("gsettings")
       .args(["get", schema, "mode"])
       .output()?;
-    let mode = std::str::from_utf8(&mode.stdout)?;
-    let enable = mode == "manual";
+    let mode = std::str::from_utf8(&mode.stdout)?.trim();
+    let enable = mode == "'manual'" || mode == "'auto'";
+
+    // get the auto config url
+    let pac_url = match mode == "'auto'" {
+      true => {
+        let url = Command::new("gsettings")
+          .args(["get", schema, "autoconfig-url"])
+          .output()?;
+        let url = std::str::from_utf8(&url.stdout)?;
+        Some(url.trim().trim_matches('\'').to_string())
+      }
+      false => None,
+    };
 
     // get bypass
     // Todo: parse the ignore-hosts
@@ -278,6 +488,8 @@ impl SysProxyConfig {
       enable,
       server,
       bypass,
+      pac_url,
+      protocol: None,
     })
   }
 
@@ -291,6 +503,18 @@ impl SysProxyConfig {
     let bypass = self.bypass.clone();
     let schema = "org.gnome.system.proxy";
 
+    // use the auto config url
+    if let Some(pac_url) = self.pac_url.as_ref().filter(|_| enable) {
+      let pac_url = format!("'{pac_url}'");
+      Command::new("gsettings")
+        .args(["set", schema, "autoconfig-url", pac_url.as_str()])
+        .status()?;
+      Command::new("gsettings")
+        .args(["set", schema, "mode", "'auto'"])
+        .status()?;
+      return Ok(());
+    }
+
     if enable {
       let mut split = server.split(":");
       let host = split.next();
@@ -303,30 +527,25 @@ impl SysProxyConfig {
       let host = format!("'{}'", host.unwrap_or("127.0.0.1"));
       let host = host.as_str();
       let port = port.unwrap();
+      let (http, socks) = self.protocols();
 
-      let http = format!("{schema}.http");
-      Command::new("gsettings")
-        .args(["set", http.as_str(), "host", host])
-        .status()?;
-      Command::new("gsettings")
-        .args(["set", http.as_str(), "port", port])
-        .status()?;
+      // clear the excluded ones left by the last protocol
+      let targets = [("http", http), ("https", http), ("socks", socks)];
 
-      let https = format!("{schema}.https");
-      Command::new("gsettings")
-        .args(["set", https.as_str(), "host", host])
-        .status()?;
-      Command::new("gsettings")
-        .args(["set", https.as_str(), "port", port])
-        .status()?;
+      for (target, included) in targets {
+        let target = format!("{schema}.{target}");
+        let (host, port) = match included {
+          true => (host, port),
+          false => ("''", "0"),
+        };
 
-      let socks = format!("{schema}.socks");
-      Command::new("gsettings")
-        .args(["set", socks.as_str(), "host", host])
-        .status()?;
-      Command::new("gsettings")
-        .args(["set", socks.as_str(), "port", port])
-        .status()?;
+        Command::new("gsettings")
+          .args(["set", target.as_str(), "host", host])
+          .status()?;
+        Command::new("gsettings")
+          .args(["set", target.as_str(), "port", port])
+          .status()?;
+      }
 
       // set bypass
       // Todo: parse the ignore-hosts
@@ -364,3 +583,103 @@ impl SysProxyConfig {
     Ok(format!("{host}:{port}"))
   }
 }
+
+/// flush the dns cache of the os
+/// avoid resolving to the stale fake ips
+#[cfg(any(target_os = "windows", target_os = "macos", target_os = "linux"))]
+pub fn flush_dns() -> Result<()> {
+  use anyhow::bail;
+  use std::process::Command;
+
+  #[cfg(target_os = "windows")]
+  let status = {
+    use std::os::windows::process::CommandExt;
+
+    Command::new("ipconfig")
+      .arg("/flushdns")
+      .creation_flags(0x08000000)
+      .status()?
+  };
+
+  #[cfg(target_os = "macos")]
+  let status = Command::new("dscacheutil").arg("-flushcache").status()?;
+
+  #[cfg(target_os = "linux")]
+  let status = Command::new("resolvectl").arg("flush-caches").status()?;
+
+  if !status.success() {
+    bail!("failed to flush the dns cache for {status}");
+  }
+
+  Ok(())
+}
+
+#[cfg(not(any(target_os = "windows", target_os = "macos", target_os = "linux")))]
+pub fn flush_dns() -> Result<()> {
+  anyhow::bail!("flushing the dns cache is unsupported on this os")
+}
+
+#[test]
+fn test_gen_pac() {
+  let pac = SysProxyConfig::gen_pac(
+    "7890",
+    Some("localhost;127.*;192.168.0.0/16;<local>".into()),
+  );
+
+  assert!(pac.contains("if (shExpMatch(host, \"localhost\")) return \"DIRECT\";"));
+  assert!(pac.contains("if (shExpMatch(host, \"127.*\")) return \"DIRECT\";"));
+  assert!(pac.contains("if (isInNet(host, \"192.168.0.0\", \"255.255.0.0\")) return \"DIRECT\";"));
+  assert!(pac.contains("if (isPlainHostName(host)) return \"DIRECT\";"));
+  assert!(pac.contains("return \"PROXY 127.0.0.1:7890; SOCKS5 127.0.0.1:7890; DIRECT\";"));
+
+  // the default bypass is always kept
+  let pac = SysProxyConfig::gen_pac("7890", Some("example.com".into()));
+  assert!(pac.contains("if (shExpMatch(host, \"example.com\")) return \"DIRECT\";"));
+  assert!(pac.contains("if (shExpMatch(host, \"localhost\")) return \"DIRECT\";"));
+}
+
+#[test]
+fn test_merge_bypass() {
+  let merged = SysProxyConfig::merge_bypass(Some("example.com;localhost,\nexample.com".into()));
+  let list = merged.split(BYPASS_SEP).collect::<Vec<_>>();
+
+  assert_eq!(list.iter().filter(|h| **h == "example.com").count(), 1);
+  assert_eq!(list.iter().filter(|h| **h == "localhost").count(), 1);
+  assert_eq!(
+    SysProxyConfig::merge_bypass(None),
+    SysProxyConfig::merge_bypass(Some(DEFAULT_BYPASS.into()))
+  );
+}
+
+#[test]
+fn test_protocols() {
+  let mut sysproxy = SysProxyConfig::new(true, "7890".into(), None);
+  assert_eq!(sysproxy.protocols(), (true, true));
+
+  sysproxy.protocol = Some("http".into());
+  assert_eq!(sysproxy.protocols(), (true, false));
+
+  sysproxy.protocol = Some("socks".into());
+  assert_eq!(sysproxy.protocols(), (false, true));
+}
+
+#[test]
+fn test_is_same() {
+  let cur = SysProxyConfig::new(true, "7890".into(), None);
+  let mut sys = cur.clone();
+  assert!(cur.is_same(&sys));
+
+  sys.server = "http=127.0.0.1:7890;https=127.0.0.1:7890".into();
+  assert!(cur.is_same(&sys));
+
+  sys.server = "127.0.0.1:8080".into();
+  assert!(!cur.is_same(&sys));
+
+  sys.enable = false;
+  assert!(!cur.is_same(&sys));
+
+  let mut pac = cur.clone();
+  pac.pac_url = Some("http://127.0.0.1:33331/commands/pac".into());
+  assert!(!pac.is_same(&cur));
+  assert!(pac.is_same(&pac));
+}
@@ -45,6 +45,8 @@ rules:
 /// enhanced profile
 pub const ITEM_MERGE: &str = "# Merge Template for clash verge
 # The `Merge` format used to enhance profile
+# The other keys will be deep merged to the profile
+# except the ports, mode, log-level and external-controller
 
 prepend-rules:
 
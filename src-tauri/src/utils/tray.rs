@@ -0,0 +1,170 @@
+use super::{resolve, window};
+use crate::{
+  cmds,
+  core::{Clash, Profiles, Verge},
+  log_if_err, states,
+};
+use std::sync::atomic::{AtomicBool, Ordering};
+use tauri::{
+  api, AppHandle, CustomMenuItem, Icon, Manager, SystemTrayEvent, SystemTrayMenu,
+  SystemTrayMenuItem, SystemTraySubmenu,
+};
+
+/// the clash modes that could be switched from the tray
+const CLASH_MODES: [&str; 3] = ["rule", "global", "direct"];
+
+/// whether the system proxy is set by the last known `cur_sysproxy`
+/// the core events could not lock the verge to read it
+static SYSPROXY_SET: AtomicBool = AtomicBool::new(false);
+
+/// whether the theme is dark by the last known verge config
+static THEME_DARK: AtomicBool = AtomicBool::new(false);
+
+/// generate the tray menu
+/// the profiles submenu will be filled after setup
+pub fn tray_menu() -> SystemTrayMenu {
+  build_menu("rule", &Profiles::default())
+}
+
+/// rebuild the tray menu
+/// should be called whenever the profiles change
+pub fn update_menu(app_handle: &AppHandle, profiles: &Profiles) {
+  let mode = Clash::read_mode();
+  let menu = build_menu(&mode, profiles);
+  log_if_err!(app_handle.tray_handle().set_menu(menu));
+}
+
+fn build_menu(mode: &str, profiles: &Profiles) -> SystemTrayMenu {
+  let current = profiles.get_current();
+  let mut profiles_menu = SystemTrayMenu::new();
+
+  // only the `remote` and `local` profile could be selected
+  for item in profiles.get_items().iter() {
+    if item.itype == Some("script".into()) || item.itype == Some("merge".into()) {
+      continue;
+    }
+
+    if let Some(uid) = item.uid.clone() {
+      let name = item.name.clone().unwrap_or(uid.clone());
+      let mut menu_item = CustomMenuItem::new(format!("profile_{uid}"), name);
+
+      if current == Some(uid) {
+        menu_item = menu_item.selected();
+      }
+      profiles_menu = profiles_menu.add_item(menu_item);
+    }
+  }
+
+  let mut menu = SystemTrayMenu::new()
+    .add_item(CustomMenuItem::new("open_window", "Show"))
+    .add_native_item(SystemTrayMenuItem::Separator);
+
+  for each in CLASH_MODES {
+    let title = format!("{}{} Mode", each[..1].to_uppercase(), &each[1..]);
+    let mut menu_item = CustomMenuItem::new(format!("{each}_mode"), title);
+
+    if each == mode {
+      menu_item = menu_item.selected();
+    }
+    menu = menu.add_item(menu_item);
+  }
+
+  menu
+    .add_submenu(SystemTraySubmenu::new("Profiles", profiles_menu))
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new("restart_clash", "Restart Clash"))
+    .add_native_item(SystemTrayMenuItem::Separator)
+    .add_item(CustomMenuItem::new("quit", "Quit").accelerator("CmdOrControl+Q"))
+}
+
+/// checkmark the current clash mode
+pub fn update_mode(app_handle: &AppHandle, mode: &str) {
+  let tray = app_handle.tray_handle();
+
+  for each in CLASH_MODES {
+    let item = tray.get_item(&format!("{each}_mode"));
+    log_if_err!(item.set_selected(each == mode));
+  }
+}
+
+/// switch the tray icon by the state
+/// gray if the core is down, badged if the system proxy is set
+/// pass the verge to refresh the system proxy state
+pub fn update_icon(app_handle: &AppHandle, core_running: bool, verge: Option<&Verge>) {
+  if let Some(verge) = verge {
+    let enable = verge.cur_sysproxy.as_ref().map_or(false, |s| s.enable);
+    SYSPROXY_SET.store(enable, Ordering::SeqCst);
+    THEME_DARK.store(verge.config.is_dark(), Ordering::SeqCst);
+  }
+
+  let dark = THEME_DARK.load(Ordering::SeqCst);
+  let proxy = SYSPROXY_SET.load(Ordering::SeqCst);
+
+  // the light icons are used on the dark theme
+  let icon: &[u8] = match (core_running, proxy, dark) {
+    (false, _, false) => include_bytes!("../../icons/tray-icon-disabled.png"),
+    (false, _, true) => include_bytes!("../../icons/tray-icon-disabled-dark.png"),
+    (true, true, false) => include_bytes!("../../icons/tray-icon-proxy.png"),
+    (true, true, true) => include_bytes!("../../icons/tray-icon-proxy-dark.png"),
+    (true, false, false) => include_bytes!("../../icons/icon.png"),
+    (true, false, true) => include_bytes!("../../icons/tray-icon-dark.png"),
+  };
+
+  log_if_err!(app_handle.tray_handle().set_icon(Icon::Raw(icon.to_vec())));
+}
+
+/// handle the tray event
+pub fn on_system_tray_event(app_handle: &AppHandle, event: SystemTrayEvent) {
+  match event {
+    SystemTrayEvent::MenuItemClick { id, .. } => match id.as_str() {
+      "open_window" => {
+        let window = app_handle.get_window("main").unwrap();
+        window.unminimize().unwrap();
+        window.show().unwrap();
+        window.set_focus().unwrap();
+      }
+      mode @ ("rule_mode" | "global_mode" | "direct_mode") => {
+        let mode = mode.trim_end_matches("_mode");
+        resolve::resolve_mode(app_handle, mode);
+      }
+      id if id.starts_with("profile_") => {
+        let uid = id.trim_start_matches("profile_").to_string();
+
+        // same as the `select_profile`
+        log_if_err!(cmds::select_profile(
+          uid,
+          app_handle.clone(),
+          app_handle.state::<states::ClashState>(),
+          app_handle.state::<states::ProfilesState>(),
+        ));
+      }
+      "restart_clash" => {
+        let clash_state = app_handle.state::<states::ClashState>();
+        let verge_state = app_handle.state::<states::VergeState>();
+        let profiles_state = app_handle.state::<states::ProfilesState>();
+        let mut clash = clash_state.0.lock().unwrap();
+        let mut verge = verge_state.0.lock().unwrap();
+        let mut profiles = profiles_state.0.lock().unwrap();
+
+        log_if_err!(clash.restart_sidecar(&mut profiles));
+        log_if_err!(verge.update_port(clash.info.port.clone()));
+      }
+      "quit" => {
+        window::save_main_window_state(app_handle);
+        resolve::resolve_reset(app_handle);
+        api::process::kill_children();
+        std::process::exit(0);
+      }
+      _ => {}
+    },
+    SystemTrayEvent::LeftClick { .. } => {
+      if cfg![target_os = "windows"] {
+        let window = app_handle.get_window("main").unwrap();
+        window.unminimize().unwrap();
+        window.show().unwrap();
+        window.set_focus().unwrap();
+      }
+    }
+    _ => {}
+  }
+}
@@ -0,0 +1,150 @@
+use super::dirs;
+use crate::{core::VergeConfig, log_if_err};
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+  fs,
+  sync::atomic::{AtomicUsize, Ordering},
+  time::Duration,
+};
+use tauri::{AppHandle, Manager, PhysicalPosition, PhysicalSize, Position, Size, Window};
+use tokio::time::sleep;
+
+/// ### `window-state.json` schema
+/// the main window's geometry
+#[derive(Default, Debug, Clone, Deserialize, Serialize)]
+pub struct WindowState {
+  pub x: i32,
+  pub y: i32,
+  pub width: u32,
+  pub height: u32,
+  pub maximized: bool,
+}
+
+/// count the move/resize events for debounce
+static SAVE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+impl WindowState {
+  /// read the state from the file
+  pub fn read_file() -> Option<Self> {
+    let json_str = fs::read_to_string(dirs::window_state_path()).ok()?;
+    serde_json::from_str::<Self>(&json_str).ok()
+  }
+
+  /// save the state to the file
+  pub fn save_file(&self) -> Result<()> {
+    let path = dirs::window_state_path();
+    let json_str = serde_json::to_string(self)?;
+    fs::write(path, json_str.as_bytes()).context("failed to save the window state")
+  }
+
+  /// get the state from the window
+  /// keep the last geometry if the window is maximized
+  pub fn from_window(window: &Window) -> Result<Self> {
+    if window.is_maximized()? {
+      let mut state = WindowState::read_file().unwrap_or_default();
+      state.maximized = true;
+      return Ok(state);
+    }
+
+    let position = window.outer_position()?;
+    let size = window.inner_size()?;
+
+    Ok(WindowState {
+      x: position.x,
+      y: position.y,
+      width: size.width,
+      height: size.height,
+      maximized: false,
+    })
+  }
+
+  /// apply the state to the window
+  /// center the window if the saved position is off-screen
+  pub fn apply(&self, window: &Window) -> Result<()> {
+    if self.width > 0 && self.height > 0 {
+      window.set_size(Size::Physical(PhysicalSize {
+        width: self.width,
+        height: self.height,
+      }))?;
+    }
+
+    let on_screen = window.available_monitors()?.iter().any(|monitor| {
+      let pos = monitor.position();
+      let size = monitor.size();
+
+      self.x >= pos.x
+        && self.y >= pos.y
+        && self.x < pos.x + size.width as i32
+        && self.y < pos.y + size.height as i32
+    });
+
+    match on_screen {
+      true => window.set_position(Position::Physical(PhysicalPosition {
+        x: self.x,
+        y: self.y,
+      }))?,
+      false => window.center()?,
+    };
+
+    if self.maximized {
+      window.maximize()?;
+    }
+
+    Ok(())
+  }
+}
+
+/// save the window state
+/// do nothing while the window is hidden
+pub fn save_window_state(window: &Window) {
+  match window.is_visible() {
+    Ok(true) => match WindowState::from_window(window) {
+      Ok(state) => log_if_err!(state.save_file()),
+      Err(err) => log::error!("failed to get the window state for {err}"),
+    },
+    _ => {}
+  }
+}
+
+/// save the main window state before exiting
+/// the exit paths skip the close event
+pub fn save_main_window_state(app_handle: &AppHandle) {
+  if let Some(window) = app_handle.get_window("main") {
+    save_window_state(&window);
+  }
+}
+
+/// save the window state after the moving/resizing settled
+pub fn save_window_state_later(window: Window) {
+  let count = SAVE_COUNTER.fetch_add(1, Ordering::SeqCst) + 1;
+
+  tauri::async_runtime::spawn(async move {
+    sleep(Duration::from_millis(500)).await;
+
+    if SAVE_COUNTER.load(Ordering::SeqCst) == count {
+      save_window_state(&window);
+    }
+  });
+}
+
+/// apply the window effect tinted by the theme
+/// the effects are only supported on windows now
+#[cfg(target_os = "windows")]
+pub fn apply_effect(window: &Window, config: &VergeConfig) {
+  use window_vibrancy::{apply_acrylic, apply_blur};
+
+  let tint = match config.is_dark() {
+    true => Some((18, 18, 18, 160)),
+    false => Some((240, 240, 240, 120)),
+  };
+
+  match config.window_effect.as_deref() {
+    Some("none") => {}
+    Some("acrylic") => log_if_err!(apply_acrylic(window, tint)),
+    _ => log_if_err!(apply_blur(window, tint)),
+  }
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn apply_effect(_: &Window, _: &VergeConfig) {}
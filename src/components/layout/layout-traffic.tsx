@@ -1,23 +1,18 @@
 import useSWR from "swr";
 import { useEffect, useState } from "react";
-import { useRecoilValue } from "recoil";
 import { Box, Typography } from "@mui/material";
 import { ArrowDownward, ArrowUpward } from "@mui/icons-material";
 import { listen } from "@tauri-apps/api/event";
 import { ApiType } from "../../services/types";
-import { getInfomation } from "../../services/api";
 import { getVergeConfig } from "../../services/cmds";
-import { atomClashPort } from "../../services/states";
 import useLogSetup from "./use-log-setup";
 import useTrafficGraph from "./use-traffic-graph";
 import parseTraffic from "../../utils/parse-traffic";
 
 // setup the traffic
 const LayoutTraffic = () => {
-  const portValue = useRecoilValue(atomClashPort);
   const [traffic, setTraffic] = useState({ up: 0, down: 0 });
   const { canvasRef, appendData, toggleStyle } = useTrafficGraph();
-  const [refresh, setRefresh] = useState({});
 
   // whether hide traffic graph
   const { data } = useSWR("getVergeConfig", getVergeConfig);
@@ -27,33 +22,17 @@ const LayoutTraffic = () => {
   useLogSetup();
 
   useEffect(() => {
-    // should reconnect the traffic ws
-    const unlisten = listen("verge://refresh-clash-config", () =>
-      setRefresh({})
-    );
+    // the traffic is forwarded by the backend
+    const unlisten = listen<ApiType.TrafficItem>("verge://traffic", (event) => {
+      appendData(event.payload);
+      setTraffic(event.payload);
+    });
 
     return () => {
       unlisten.then((fn) => fn());
     };
   }, []);
 
-  useEffect(() => {
-    let ws: WebSocket | null = null;
-
-    getInfomation().then((result) => {
-      const { server = "", secret = "" } = result;
-      ws = new WebSocket(`ws://${server}/traffic?token=${secret}`);
-
-      ws.addEventListener("message", (event) => {
-        const data = JSON.parse(event.data) as ApiType.TrafficItem;
-        appendData(data);
-        setTraffic(data);
-      });
-    });
-
-    return () => ws?.close();
-  }, [portValue, refresh]);
-
   const [up, upUnit] = parseTraffic(traffic.up);
   const [down, downUnit] = parseTraffic(traffic.down);
 
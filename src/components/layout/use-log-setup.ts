@@ -1,43 +1,32 @@
 import dayjs from "dayjs";
-import { useEffect, useState } from "react";
+import { useEffect } from "react";
 import { useSetRecoilState } from "recoil";
 import { listen } from "@tauri-apps/api/event";
 import { ApiType } from "../../services/types";
-import { getInfomation } from "../../services/api";
+import { startLogStream, stopLogStream } from "../../services/cmds";
 import { atomLogData } from "../../services/states";
 
 const MAX_LOG_NUM = 1000;
 
-// setup the log websocket
+// setup the log stream
+// the backend reconnects when the core restarts
 export default function useLogSetup() {
-  const [refresh, setRefresh] = useState({});
   const setLogData = useSetRecoilState(atomLogData);
 
   useEffect(() => {
-    let ws: WebSocket = null!;
-
-    const handler = (event: MessageEvent<any>) => {
-      const data = JSON.parse(event.data) as ApiType.LogItem;
+    const unlisten = listen<ApiType.LogItem>("verge://log", (event) => {
       const time = dayjs().format("MM-DD HH:mm:ss");
       setLogData((l) => {
         if (l.length >= MAX_LOG_NUM) l.shift();
-        return [...l, { ...data, time }];
+        return [...l, { ...event.payload, time }];
       });
-    };
-
-    getInfomation().then((info) => {
-      const { server = "", secret = "" } = info;
-      ws = new WebSocket(`ws://${server}/logs?token=${secret}`);
-      ws.addEventListener("message", handler);
     });
 
-    const unlisten = listen("verge://refresh-clash-config", () =>
-      setRefresh({})
-    );
+    startLogStream().catch(console.error);
 
     return () => {
-      ws?.close();
+      stopLogStream().catch(console.error);
       unlisten?.then((fn) => fn());
     };
-  }, [refresh]);
+  }, []);
 }
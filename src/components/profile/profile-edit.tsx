@@ -7,7 +7,9 @@ import {
   DialogActions,
   DialogContent,
   DialogTitle,
+  FormControlLabel,
   IconButton,
+  Switch,
   TextField,
 } from "@mui/material";
 import { Settings } from "@mui/icons-material";
@@ -42,12 +44,21 @@ const ProfileEdit = (props: Props) => {
       const { uid } = itemData;
       const { name, desc, url } = form;
       const option_ = itemData.type === "remote" ? option : undefined;
+      const autoUpdate =
+        itemData.type === "remote" ? form.auto_update ?? true : undefined;
 
       if (itemData.type === "remote" && !url) {
         throw new Error("Remote URL should not be null");
       }
 
-      await patchProfile(uid, { uid, name, desc, url, option: option_ });
+      await patchProfile(uid, {
+        uid,
+        name,
+        desc,
+        url,
+        auto_update: autoUpdate,
+        option: option_,
+      });
       setShowOpt(false);
       mutate("getProfiles");
       onClose();
@@ -112,6 +123,18 @@ const ProfileEdit = (props: Props) => {
             onChange={(e) => setOption({ user_agent: e.target.value })}
           />
         )}
+
+        {type === "remote" && (
+          <FormControlLabel
+            label="Auto Update"
+            control={
+              <Switch
+                checked={form.auto_update ?? true}
+                onChange={(_, c) => setForm({ auto_update: c })}
+              />
+            }
+          />
+        )}
       </DialogContent>
 
       <DialogActions sx={{ px: 2, pb: 2, position: "relative" }}>
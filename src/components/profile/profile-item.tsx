@@ -14,9 +14,15 @@ import {
   MenuItem,
   Menu,
 } from "@mui/material";
-import { RefreshRounded } from "@mui/icons-material";
+import { RefreshRounded, WarningRounded } from "@mui/icons-material";
 import { CmdType } from "../../services/types";
-import { updateProfile, deleteProfile, viewProfile } from "../../services/cmds";
+import {
+  updateProfile,
+  cancelUpdate,
+  deleteProfile,
+  viewProfile,
+  openProfileHome,
+} from "../../services/cmds";
 import parseTraffic from "../../utils/parse-traffic";
 import ProfileEdit from "./profile-edit";
 import Notice from "../base/base-notice";
@@ -87,6 +93,15 @@ const ProfileItem = (props: Props) => {
     }
   };
 
+  const onHome = async () => {
+    setAnchorEl(null);
+    try {
+      await openProfileHome(itemData.uid);
+    } catch (err: any) {
+      Notice.error(err?.message || err.toString());
+    }
+  };
+
   const onForceSelect = () => {
     setAnchorEl(null);
     onSelect(true);
@@ -102,6 +117,17 @@ const ProfileItem = (props: Props) => {
       mutate("getProfiles");
     } catch (err: any) {
       setLoading(false);
+      if (err === "UPDATE_CANCELED") return;
+      mutate("getProfiles"); // show the last error
+      Notice.error(err?.message || err.toString());
+    }
+  };
+
+  const onCancel = async () => {
+    setAnchorEl(null);
+    try {
+      await cancelUpdate(itemData.uid);
+    } catch (err: any) {
       Notice.error(err?.message || err.toString());
     }
   };
@@ -112,6 +138,17 @@ const ProfileItem = (props: Props) => {
       await deleteProfile(itemData.uid);
       mutate("getProfiles");
     } catch (err: any) {
+      // the current profile requires the confirmation
+      if (err === "DELETE_CURRENT_PROFILE") {
+        if (!window.confirm(t("Delete Current Profile"))) return;
+        try {
+          await deleteProfile(itemData.uid, true);
+          mutate("getProfiles");
+        } catch (err: any) {
+          Notice.error(err?.message || err.toString());
+        }
+        return;
+      }
       Notice.error(err?.message || err.toString());
     }
   });
@@ -127,8 +164,13 @@ const ProfileItem = (props: Props) => {
     { label: "Select", handler: onForceSelect },
     { label: "Edit", handler: onEdit },
     { label: "File", handler: onView },
-    { label: "Update", handler: onUpdateWrapper(false) },
-    { label: "Update(Proxy)", handler: onUpdateWrapper(true) },
+    ...(itemData.home ? [{ label: "Home", handler: onHome }] : []),
+    ...(loading
+      ? [{ label: "Cancel Update", handler: onCancel }]
+      : [
+          { label: "Update", handler: onUpdateWrapper(false) },
+          { label: "Update(Proxy)", handler: onUpdateWrapper(true) },
+        ]),
     { label: "Delete", handler: onDelete },
   ];
   const fileModeMenu = [
@@ -219,8 +261,14 @@ const ProfileItem = (props: Props) => {
               flex="1 0 auto"
               fontSize={14}
               textAlign="right"
-              title="updated time"
+              title={itemData.last_error ?? "updated time"}
             >
+              {itemData.last_error && (
+                <WarningRounded
+                  color="warning"
+                  sx={{ fontSize: 16, mr: 0.5, verticalAlign: "middle" }}
+                />
+              )}
               {fromnow}
             </Typography>
           </Box>
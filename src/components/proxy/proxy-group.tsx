@@ -25,7 +25,7 @@ import {
 } from "@mui/icons-material";
 import { ApiType } from "../../services/types";
 import { updateProxy } from "../../services/api";
-import { getProfiles, patchProfile } from "../../services/cmds";
+import { getProfiles, patchSelected } from "../../services/cmds";
 import delayManager from "../../services/delay";
 import useFilterProxy from "./use-filter-proxy";
 import ProxyItem from "./proxy-item";
@@ -62,20 +62,9 @@ const ProxyGroup = ({ group }: Props) => {
     }
 
     try {
-      const profile = profiles?.items?.find((p) => p.uid === profiles.current);
-      if (!profile) return;
-      if (!profile.selected) profile.selected = [];
-
-      const index = profile.selected.findIndex(
-        (item) => item.name === group.name
-      );
-
-      if (index < 0) {
-        profile.selected.push({ name: group.name, now: name });
-      } else {
-        profile.selected[index] = { name: group.name, now: name };
-      }
-      await patchProfile(profiles!.current!, { selected: profile.selected });
+      // remembered by the current profile only
+      if (!profiles?.current) return;
+      await patchSelected(profiles.current, { name: group.name, now: name });
     } catch (err) {
       console.error(err);
     }
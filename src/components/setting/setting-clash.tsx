@@ -32,6 +32,8 @@ const SettingClash = ({ onError }: Props) => {
     "allow-lan": allowLan,
     "log-level": logLevel,
     "mixed-port": mixedPort,
+    "socks-port": socksPort,
+    port: httpPort,
   } = clashConfig ?? {};
 
   const setGlobalClashPort = useSetRecoilState(atomClashPort);
@@ -45,20 +47,23 @@ const SettingClash = ({ onError }: Props) => {
     await patchClashConfig(patch);
   };
 
-  const onUpdatePort = async (port: number) => {
-    if (port < 1000) {
-      throw new Error("The port should not < 1000");
-    }
-    if (port > 65536) {
-      throw new Error("The port should not > 65536");
-    }
-    await patchClashConfig({ "mixed-port": port });
-    setGlobalClashPort(port);
-    Notice.success("Change Clash port successfully!");
-
-    // update the config
-    mutate("getClashConfig");
-  };
+  // the conflicts are checked by the backend
+  // only the mixed port is required, the others could be 0 to disable
+  const onUpdatePort =
+    (key: "mixed-port" | "socks-port" | "port") => async (port: number) => {
+      if (port < 1000 && (key === "mixed-port" || port !== 0)) {
+        throw new Error("The port should not < 1000");
+      }
+      if (port > 65535) {
+        throw new Error("The port should not > 65535");
+      }
+      await patchClashConfig({ [key]: port });
+      if (key === "mixed-port") setGlobalClashPort(port);
+      Notice.success("Change Clash port successfully!");
+
+      // update the config
+      mutate("getClashConfig");
+    };
 
   // get clash core version
   const clashVer = versionData?.premium
@@ -121,7 +126,35 @@ const SettingClash = ({ onError }: Props) => {
           onCatch={onError}
           onFormat={(e: any) => +e.target.value?.replace(/\D+/, "")}
           onChange={(e) => onChangeData({ "mixed-port": e })}
-          onGuard={onUpdatePort}
+          onGuard={onUpdatePort("mixed-port")}
+          waitTime={800}
+        >
+          <TextField autoComplete="off" size="small" sx={{ width: 120 }} />
+        </GuardState>
+      </SettingItem>
+
+      <SettingItem>
+        <ListItemText primary={t("Socks Port")} />
+        <GuardState
+          value={socksPort ?? 0}
+          onCatch={onError}
+          onFormat={(e: any) => +e.target.value?.replace(/\D+/, "")}
+          onChange={(e) => onChangeData({ "socks-port": e })}
+          onGuard={onUpdatePort("socks-port")}
+          waitTime={800}
+        >
+          <TextField autoComplete="off" size="small" sx={{ width: 120 }} />
+        </GuardState>
+      </SettingItem>
+
+      <SettingItem>
+        <ListItemText primary={t("Http Port")} />
+        <GuardState
+          value={httpPort ?? 0}
+          onCatch={onError}
+          onFormat={(e: any) => +e.target.value?.replace(/\D+/, "")}
+          onChange={(e) => onChangeData({ port: e })}
+          onGuard={onUpdatePort("port")}
           waitTime={800}
         >
           <TextField autoComplete="off" size="small" sx={{ width: 120 }} />
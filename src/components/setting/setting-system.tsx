@@ -19,6 +19,7 @@ const SettingSystem = ({ onError }: Props) => {
   const {
     enable_tun_mode,
     enable_auto_launch,
+    register_scheme,
     enable_system_proxy,
     system_proxy_bypass,
     enable_proxy_guard,
@@ -59,6 +60,20 @@ const SettingSystem = ({ onError }: Props) => {
         </GuardState>
       </SettingItem>
 
+      <SettingItem>
+        <ListItemText primary={t("Handle Clash Links")} />
+        <GuardState
+          value={register_scheme ?? false}
+          valueProps="checked"
+          onCatch={onError}
+          onFormat={onSwitchFormat}
+          onChange={(e) => onChangeData({ register_scheme: e })}
+          onGuard={(e) => patchVergeConfig({ register_scheme: e })}
+        >
+          <Switch edge="end" />
+        </GuardState>
+      </SettingItem>
+
       <SettingItem>
         <ListItemText
           primary={
@@ -12,6 +12,7 @@ import {
   getVergeConfig,
   openAppDir,
   openLogsDir,
+  openProfileTemplate,
   patchVergeConfig,
 } from "../../services/cmds";
 import { ArrowForward } from "@mui/icons-material";
@@ -40,21 +41,39 @@ const SettingVerge = ({ onError }: Props) => {
   return (
     <SettingList title={t("Verge Setting")}>
       <SettingItem>
-        <ListItemText primary={t("Theme Mode")} />
+        <ListItemText primary={t("Follow System Theme")} />
         <GuardState
-          value={theme_mode === "dark"}
+          value={theme_mode === "system"}
           valueProps="checked"
           onCatch={onError}
           onFormat={onSwitchFormat}
-          onChange={(e) => onChangeData({ theme_mode: e ? "dark" : "light" })}
+          onChange={(e) => onChangeData({ theme_mode: e ? "system" : "light" })}
           onGuard={(e) =>
-            patchVergeConfig({ theme_mode: e ? "dark" : "light" })
+            patchVergeConfig({ theme_mode: e ? "system" : "light" })
           }
         >
-          <PaletteSwitch edge="end" />
+          <Switch edge="end" />
         </GuardState>
       </SettingItem>
 
+      {theme_mode !== "system" && (
+        <SettingItem>
+          <ListItemText primary={t("Theme Mode")} />
+          <GuardState
+            value={theme_mode === "dark"}
+            valueProps="checked"
+            onCatch={onError}
+            onFormat={onSwitchFormat}
+            onChange={(e) => onChangeData({ theme_mode: e ? "dark" : "light" })}
+            onGuard={(e) =>
+              patchVergeConfig({ theme_mode: e ? "dark" : "light" })
+            }
+          >
+            <PaletteSwitch edge="end" />
+          </GuardState>
+        </SettingItem>
+      )}
+
       <SettingItem>
         <ListItemText primary={t("Theme Blur")} />
         <GuardState
@@ -113,6 +132,13 @@ const SettingVerge = ({ onError }: Props) => {
         </IconButton>
       </SettingItem>
 
+      <SettingItem>
+        <ListItemText primary={t("Profile Template")} />
+        <IconButton color="inherit" size="small" onClick={openProfileTemplate}>
+          <ArrowForward />
+        </IconButton>
+      </SettingItem>
+
       <SettingItem>
         <ListItemText primary={t("Version")} />
         <Typography sx={{ py: "6px" }}>v{version}</Typography>
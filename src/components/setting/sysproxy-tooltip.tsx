@@ -25,6 +25,7 @@ const SysproxyTooltip = () => {
       <div>Enable: {(!!info.enable).toString()}</div>
       <div>Server: {info.server}</div>
       <div>Bypass: {info.bypass}</div>
+      {info.tampered && <div>Overridden by another app</div>}
     </div>
   );
 
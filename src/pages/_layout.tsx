@@ -2,7 +2,7 @@ import dayjs from "dayjs";
 import i18next from "i18next";
 import relativeTime from "dayjs/plugin/relativeTime";
 import useSWR, { SWRConfig, useSWRConfig } from "swr";
-import { useEffect, useMemo } from "react";
+import { useEffect, useMemo, useState } from "react";
 import { useTranslation } from "react-i18next";
 import { Route, Routes } from "react-router-dom";
 import { alpha, createTheme, List, Paper, ThemeProvider } from "@mui/material";
@@ -10,12 +10,18 @@ import { listen } from "@tauri-apps/api/event";
 import { appWindow } from "@tauri-apps/api/window";
 import { routers } from "./_routers";
 import { getAxios } from "../services/api";
-import { getVergeConfig } from "../services/cmds";
+import {
+  getVergeConfig,
+  importProfile,
+  takeLaunchError,
+  takePendingImport,
+} from "../services/cmds";
 import LogoSvg from "../assets/image/logo.svg";
 import LayoutItem from "../components/layout/layout-item";
 import LayoutControl from "../components/layout/layout-control";
 import LayoutTraffic from "../components/layout/layout-traffic";
 import UpdateButton from "../components/layout/update-button";
+import Notice from "../components/base/base-notice";
 import "dayjs/locale/zh-cn";
 
 dayjs.extend(relativeTime);
@@ -27,8 +33,28 @@ const Layout = () => {
   const { mutate } = useSWRConfig();
   const { data } = useSWR("getVergeConfig", getVergeConfig);
 
-  const blur = !!data?.theme_blur;
-  const mode = data?.theme_mode ?? "light";
+  // the window is opaque without the effect
+  const blur = !!data?.theme_blur && data?.window_effect !== "none";
+
+  // follow the os theme in the `system` mode
+  const darkQuery = "(prefers-color-scheme: dark)";
+  const [systemDark, setSystemDark] = useState(
+    () => window.matchMedia(darkQuery).matches
+  );
+
+  useEffect(() => {
+    const media = window.matchMedia(darkQuery);
+    const handler = (e: MediaQueryListEvent) => setSystemDark(e.matches);
+    media.addEventListener("change", handler);
+    return () => media.removeEventListener("change", handler);
+  }, []);
+
+  const mode =
+    data?.theme_mode === "system"
+      ? systemDark
+        ? "dark"
+        : "light"
+      : data?.theme_mode ?? "light";
 
   useEffect(() => {
     window.addEventListener("keydown", (e) => {
@@ -41,6 +67,37 @@ const Layout = () => {
       mutate("getProxies");
       mutate("getClashConfig");
     });
+
+    // the verge config may be changed by the hotkeys
+    listen("verge://refresh-verge-config", () => mutate("getVergeConfig"));
+
+    // the profile of the deep link is imported after confirmation
+    const confirmImport = async () => {
+      const url = await takePendingImport();
+      if (!url || !window.confirm(`${t("Import Deep Link")}\n${url}`)) return;
+
+      try {
+        await importProfile(url);
+        mutate("getProfiles");
+        Notice.success("Successfully import profile.");
+      } catch (err: any) {
+        Notice.error(err?.message || err.toString());
+      }
+    };
+
+    confirmImport();
+    listen("verge://import-confirm", confirmImport);
+    listen("verge://refresh-profiles", () => mutate("getProfiles"));
+    // the os theme is also checked by the core side
+    listen<boolean>("verge://theme-changed", (event) =>
+      setSystemDark(event.payload)
+    );
+    listen<string>("verge://notice-error", (event) =>
+      Notice.error(event.payload)
+    );
+
+    // the core may fail to launch before the window is loaded
+    takeLaunchError().then((err) => err && Notice.error(err));
   }, []);
 
   useEffect(() => {
@@ -1,18 +1,17 @@
 import useSWR, { useSWRConfig } from "swr";
 import { useLockFn } from "ahooks";
-import { useEffect, useMemo, useState } from "react";
+import { useMemo, useState } from "react";
 import { Box, Button, Grid, TextField } from "@mui/material";
 import { useTranslation } from "react-i18next";
 import {
   getProfiles,
-  patchProfile,
   deleteProfile,
   selectProfile,
   importProfile,
+  updateAllProfiles,
   enhanceProfiles,
   changeProfileChain,
 } from "../services/cmds";
-import { getProxies, updateProxy } from "../services/api";
 import Notice from "../components/base/base-notice";
 import BasePage from "../components/base/base-page";
 import ProfileNew from "../components/profile/profile-new";
@@ -48,50 +47,6 @@ const ProfilePage = () => {
     return { regularItems, enhanceItems };
   }, [profiles]);
 
-  useEffect(() => {
-    if (profiles.current == null) return;
-
-    const current = profiles.current;
-    const profile = regularItems.find((p) => p.uid === current);
-    if (!profile) return;
-
-    setTimeout(async () => {
-      const proxiesData = await getProxies();
-      mutate("getProxies", proxiesData);
-
-      // init selected array
-      const { selected = [] } = profile;
-      const selectedMap = Object.fromEntries(
-        selected.map((each) => [each.name!, each.now!])
-      );
-
-      // todo: enhance error handle
-      let hasChange = false;
-      proxiesData.groups.forEach((group) => {
-        const { name, now } = group;
-
-        if (!now || selectedMap[name] === now) return;
-        if (selectedMap[name] == null) {
-          selectedMap[name] = now!;
-        } else {
-          hasChange = true;
-          updateProxy(name, selectedMap[name]);
-        }
-      });
-      // update profile selected list
-      profile.selected = Object.entries(selectedMap).map(([name, now]) => ({
-        name,
-        now,
-      }));
-
-      patchProfile(current!, { selected: profile.selected }).catch(
-        console.error
-      );
-      // update proxies cache
-      if (hasChange) mutate("getProxies", getProxies());
-    }, 100);
-  }, [profiles, regularItems]);
-
   const onImport = async () => {
     if (!url) return;
     setUrl("");
@@ -128,6 +83,24 @@ const ProfilePage = () => {
     }
   });
 
+  const onUpdateAll = useLockFn(async () => {
+    try {
+      const results = await updateAllProfiles();
+      const failed = results.filter((r) => !!r.error);
+
+      if (failed.length > 0) {
+        const names = failed.map((r) => r.name || r.uid).join(", ");
+        Notice.error(`${t("Failed to Update")}: ${names}`);
+      } else {
+        Notice.success(t("Profiles Updated"));
+      }
+    } catch (err: any) {
+      Notice.error(err?.message || err.toString());
+    } finally {
+      mutate("getProfiles");
+    }
+  });
+
   /** enhanced profile mode */
 
   const chain = profiles.chain || [];
@@ -197,9 +170,16 @@ const ProfilePage = () => {
         >
           {t("Import")}
         </Button>
-        <Button variant="contained" onClick={() => setDialogOpen(true)}>
+        <Button
+          variant="contained"
+          onClick={() => setDialogOpen(true)}
+          sx={{ mr: 1 }}
+        >
           {t("New")}
         </Button>
+        <Button variant="contained" onClick={onUpdateAll}>
+          {t("Update All")}
+        </Button>
       </Box>
 
       <Grid container spacing={2}>
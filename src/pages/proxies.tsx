@@ -31,9 +31,9 @@ const ProxyPage = () => {
   }, [groups, proxies, curMode]);
 
   const onChangeMode = useLockFn(async (mode: string) => {
-    // switch rapidly
-    await updateConfigs({ mode });
+    // the mode may be locked by the kill switch
     await patchClashConfig({ mode });
+    await updateConfigs({ mode });
     mutate("getClashConfig");
   });
 
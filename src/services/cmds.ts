@@ -14,6 +14,38 @@ export async function enhanceProfiles() {
   return invoke<void>("enhance_profiles");
 }
 
+export async function addRule(rule: string, position?: "prepend" | "append") {
+  return invoke<void>("add_rule", { rule, position });
+}
+
+export async function getRuleOverrides() {
+  return invoke<CmdType.RuleOverrides>("get_rule_overrides");
+}
+
+export async function clearRuleOverrides() {
+  return invoke<void>("clear_rule_overrides");
+}
+
+export async function addBypassDomain(domain: string) {
+  return invoke<void>("add_bypass_domain", { domain });
+}
+
+export async function removeBypassDomain(domain: string) {
+  return invoke<void>("remove_bypass_domain", { domain });
+}
+
+export async function listBypassDomains() {
+  return invoke<string[]>("list_bypass_domains");
+}
+
+export async function exportConfig(path?: string) {
+  return invoke<string>("export_config", { path });
+}
+
+export async function testEnhanceScript(script: string) {
+  return invoke<CmdType.ProfileData>("test_enhance_script", { script });
+}
+
 export async function createProfile(item: Partial<CmdType.ProfileItem>) {
   return invoke<void>("create_profile", { item });
 }
@@ -22,10 +54,53 @@ export async function viewProfile(index: string) {
   return invoke<void>("view_profile", { index });
 }
 
-export async function importProfile(url: string) {
+export async function openProfileHome(index: string) {
+  return invoke<void>("open_profile_home", { index });
+}
+
+export async function revealProfile(index: string) {
+  return invoke<void>("reveal_profile", { index });
+}
+
+export async function importProfile(url: string, autoSelect?: boolean) {
   return invoke<void>("import_profile", {
     url,
     option: { with_proxy: true },
+    autoSelect,
+  });
+}
+
+export async function dryRunProfile(index: string) {
+  return invoke<void>("dry_run_profile", { index });
+}
+
+export async function takePendingImport() {
+  return invoke<string | null>("take_pending_import");
+}
+
+export async function takeLaunchError() {
+  return invoke<string | null>("take_launch_error");
+}
+
+export async function importProfilesBatch(url: string) {
+  return invoke<CmdType.ProfileImportResult[]>("import_profiles_batch", {
+    url,
+    option: { with_proxy: true },
+  });
+}
+
+export async function importProfileText(text: string, name?: string) {
+  return invoke<void>("import_profile_text", { text, name });
+}
+
+export async function importProfileFile(path: string) {
+  return invoke<void>("import_profile_file", { path });
+}
+
+export async function importFromQr(imagePath: string) {
+  return invoke<void>("import_from_qr", {
+    imagePath,
+    option: { with_proxy: true },
   });
 }
 
@@ -36,8 +111,18 @@ export async function updateProfile(
   return invoke<void>("update_profile", { index, option });
 }
 
-export async function deleteProfile(index: string) {
-  return invoke<void>("delete_profile", { index });
+export async function updateAllProfiles(option?: CmdType.ProfileOption) {
+  return invoke<CmdType.ProfileUpdateResult[]>("update_all_profiles", {
+    option,
+  });
+}
+
+export async function cancelUpdate(index: string) {
+  return invoke<void>("cancel_update", { index });
+}
+
+export async function deleteProfile(index: string, force?: boolean) {
+  return invoke<void>("delete_profile", { index, force });
 }
 
 export async function patchProfile(
@@ -47,22 +132,137 @@ export async function patchProfile(
   return invoke<void>("patch_profile", { index, profile });
 }
 
+export async function patchSelected(
+  index: string,
+  selected: { name: string; now: string }
+) {
+  return invoke<void>("patch_selected", { index, selected });
+}
+
 export async function selectProfile(index: string) {
   return invoke<void>("select_profile", { index });
 }
 
+export async function reorderProfiles(uids: string[]) {
+  return invoke<void>("reorder_profiles", { uids });
+}
+
 export async function changeProfileChain(chain?: string[]) {
   return invoke<void>("change_profile_chain", { chain });
 }
 
+export async function moveProfileChain(uid: string, up: boolean) {
+  return invoke<void>("move_profile_chain", { uid, up });
+}
+
+export async function updateGeoData() {
+  return invoke<string[]>("update_geo_data");
+}
+
 export async function getClashInfo() {
   return invoke<CmdType.ClashInfo | null>("get_clash_info");
 }
 
+export async function reloadClashConfig() {
+  return invoke<void>("reload_clash_config");
+}
+
+export async function testClashSecret(secret?: string) {
+  return invoke<boolean>("test_clash_secret", { secret });
+}
+
+export async function getClashVersion() {
+  return invoke<CmdType.ClashVersion>("get_clash_version");
+}
+
+export async function getCoreStatus() {
+  return invoke<CmdType.CoreStatus>("get_core_status");
+}
+
+export async function getTraffic() {
+  return invoke<CmdType.TrafficTotal>("get_traffic");
+}
+
+export async function getConnections() {
+  return invoke<any>("get_connections");
+}
+
+export async function closeConnection(id: string) {
+  return invoke<void>("close_connection", { id });
+}
+
+export async function closeAllConnections() {
+  return invoke<void>("close_all_connections");
+}
+
+export async function getProviders() {
+  return invoke<CmdType.ProviderInfo[]>("get_providers");
+}
+
+export async function updateProvider(name: string) {
+  return invoke<void>("update_provider", { name });
+}
+
+export async function checkConnectivity(url?: string) {
+  return invoke<CmdType.ConnectivityResult>("check_connectivity", { url });
+}
+
+export async function flushFakeip(dns?: boolean) {
+  return invoke<void>("flush_fakeip", { dns });
+}
+
+export async function queryDns(domain: string, recordType?: string) {
+  return invoke<CmdType.DnsAnswer[]>("query_dns", { domain, recordType });
+}
+
+export async function testGroupDelay(
+  group: string,
+  url?: string,
+  timeout?: number
+) {
+  return invoke<Record<string, number | null>>("test_group_delay", {
+    group,
+    url,
+    timeout,
+  });
+}
+
 export async function patchClashConfig(payload: Partial<ApiType.ConfigData>) {
   return invoke<void>("patch_clash_config", { payload });
 }
 
+export async function getInterfaces() {
+  return invoke<string[]>("get_interfaces");
+}
+
+export async function setKillSwitch(enable: boolean) {
+  return invoke<void>("set_kill_switch", { enable });
+}
+
+export async function getRuntimeConfig(withFile?: boolean) {
+  return invoke<CmdType.RuntimeConfig>("get_runtime_config", { withFile });
+}
+
+export async function getRuntimeConfigPath() {
+  return invoke<string>("get_runtime_config_path");
+}
+
+export async function getClashLogLevel() {
+  return invoke<string>("get_clash_log_level");
+}
+
+export async function setClashLogLevel(level: string) {
+  return invoke<void>("set_clash_log_level", { level });
+}
+
+export async function startLogStream(level?: string) {
+  return invoke<void>("start_log_stream", { level });
+}
+
+export async function stopLogStream() {
+  return invoke<void>("stop_log_stream");
+}
+
 export async function getVergeConfig() {
   return invoke<CmdType.VergeConfig>("get_verge_config");
 }
@@ -71,8 +271,24 @@ export async function patchVergeConfig(payload: CmdType.VergeConfig) {
   return invoke<void>("patch_verge_config", { payload });
 }
 
+export async function toggleSystemProxy(enable: boolean, persist?: boolean) {
+  return invoke<void>("toggle_system_proxy", { enable, persist });
+}
+
+export async function getLaunchStatus() {
+  return invoke<CmdType.LaunchStatus>("get_launch_status");
+}
+
 export async function getSystemProxy() {
-  return invoke<any>("get_sys_proxy");
+  return invoke<CmdType.SysProxyStatus>("get_sys_proxy");
+}
+
+export async function getLocalNetworks() {
+  return invoke<string[]>("get_local_networks");
+}
+
+export async function getSystemProxyBypass() {
+  return invoke<string>("get_sys_proxy_bypass");
 }
 
 export async function restartSidecar() {
@@ -83,12 +299,42 @@ export async function killSidecars() {
   return invoke<any>("kill_sidecars");
 }
 
+export async function getStorageInfo() {
+  return invoke<CmdType.StorageInfo>("get_storage_info");
+}
+
 export async function openAppDir() {
   return invoke<void>("open_app_dir").catch((err) =>
     Notice.error(err?.message || err.toString(), 1500)
   );
 }
 
+export async function backupConfig(path: string) {
+  return invoke<void>("backup_config", { path });
+}
+
+export async function restoreConfig(path: string) {
+  return invoke<void>("restore_config", { path });
+}
+
+export async function resetAllConfig(keepProfiles?: boolean) {
+  return invoke<void>("reset_all_config", { confirm: true, keepProfiles });
+}
+
+export async function openDashboard() {
+  return invoke<void>("open_dashboard");
+}
+
+export async function openProfileTemplate() {
+  return invoke<void>("open_profile_template").catch((err) =>
+    Notice.error(err?.message || err.toString(), 1500)
+  );
+}
+
+export async function resetProfileTemplate() {
+  return invoke<void>("reset_profile_template");
+}
+
 export async function openLogsDir() {
   return invoke<void>("open_logs_dir").catch((err) =>
     Notice.error(err?.message || err.toString(), 1500)
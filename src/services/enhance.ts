@@ -1,6 +1,54 @@
 import { emit, listen } from "@tauri-apps/api/event";
 import { CmdType } from "./types";
 
+// the list directives of the merge mode
+const MERGE_DIRECTIVES = [
+  "prepend-rules",
+  "prepend-proxies",
+  "prepend-proxy-groups",
+  "append-rules",
+  "append-proxies",
+  "append-proxy-groups",
+];
+
+function isObject(value: any) {
+  return typeof value === "object" && value !== null && !Array.isArray(value);
+}
+
+/**
+ * deep merge the source to the target
+ * the arrays and other values will be replaced
+ */
+function deepMerge(target: any, source: any) {
+  const result = { ...target };
+
+  Object.keys(source).forEach((key) => {
+    const value = source[key];
+
+    if (isObject(value) && isObject(result[key])) {
+      result[key] = deepMerge(result[key], value);
+    } else {
+      result[key] = value;
+    }
+  });
+
+  return result;
+}
+
+/**
+ * get the keys to be deep merged
+ */
+function toMergeKeys(merge: CmdType.ProfileMerge) {
+  if (!merge) return [];
+
+  return Object.keys(merge).filter(
+    (key) =>
+      !MERGE_DIRECTIVES.includes(key) &&
+      merge[key] !== null &&
+      merge[key] !== undefined
+  );
+}
+
 /**
  * process the merge mode
  */
@@ -10,7 +58,11 @@ function toMerge(
 ): CmdType.ProfileData {
   if (!merge) return data;
 
-  const newData = { ...data };
+  // deep merge the other keys first
+  const others: CmdType.ProfileMerge = {};
+  toMergeKeys(merge).forEach((key) => (others[key] = merge[key]));
+
+  const newData = deepMerge(data, others);
 
   // rules
   if (Array.isArray(merge["prepend-rules"])) {
@@ -62,6 +114,57 @@ function toScript(
   return func(data);
 }
 
+/**
+ * run the script in a worker
+ * terminate it if timeout
+ */
+function toScriptInWorker(
+  script: string,
+  data: CmdType.ProfileData,
+  timeout = 5000
+): Promise<CmdType.ProfileData> {
+  const code = `'use strict';${script};
+self.onmessage = async (e) => {
+  try {
+    self.postMessage({ data: await main(e.data) });
+  } catch (err) {
+    self.postMessage({ error: (err && err.stack) || String(err) });
+  }
+};`;
+
+  const url = URL.createObjectURL(new Blob([code]));
+  const worker = new Worker(url);
+
+  const clean = () => {
+    worker.terminate();
+    URL.revokeObjectURL(url);
+  };
+
+  return new Promise((resolve, reject) => {
+    const timer = setTimeout(() => {
+      clean();
+      reject(new Error(`the script is timeout after ${timeout}ms`));
+    }, timeout);
+
+    worker.onmessage = (e) => {
+      clearTimeout(timer);
+      clean();
+      if (e.data.error) reject(new Error(e.data.error));
+      else resolve(e.data.data);
+    };
+
+    // the syntax error
+    worker.onerror = (e) => {
+      e.preventDefault();
+      clearTimeout(timer);
+      clean();
+      reject(new Error(`${e.message} (line ${e.lineno}:${e.colno})`));
+    };
+
+    worker.postMessage(data);
+  });
+}
+
 export type EStatus = { status: "ok" | "error"; message?: string };
 export type EListener = (status: EStatus) => void;
 export type EUnlistener = () => void;
@@ -102,6 +205,7 @@ class Enhance {
       let pdata = payload.current || {};
 
       let hasScript = false;
+      const mergeKeys: string[] = [];
 
       for (const each of payload.chain) {
         const { uid, type = "" } = each.item;
@@ -117,6 +221,8 @@ class Enhance {
           // process merge
           else if (type === "merge") {
             pdata = toMerge(each.merge!, { ...pdata });
+            const keys = toMergeKeys(each.merge!);
+            mergeKeys.push(...keys.map((k) => k.toLowerCase()));
           }
 
           // invalid type
@@ -136,7 +242,7 @@ class Enhance {
       }
 
       // If script is never used
-      // filter other fields
+      // filter other fields except the merged
       if (!hasScript) {
         const validKeys = [
           "proxies",
@@ -150,7 +256,7 @@ class Enhance {
         const newData: any = {};
         Object.keys(pdata).forEach((key) => {
           const newKey = key.toLowerCase();
-          if (validKeys.includes(newKey)) {
+          if (validKeys.includes(newKey) || mergeKeys.includes(newKey)) {
             newData[newKey] = (pdata as any)[key];
           }
         });
@@ -161,6 +267,23 @@ class Enhance {
       const result = { data: pdata, status: "ok" };
       emit(payload.callback, JSON.stringify(result)).catch(console.error);
     });
+
+    // test the script without applying
+    listen("script-test-handler", async (event) => {
+      const payload = event.payload as CmdType.ScriptTestPayload;
+      let result: CmdType.EnhancedResult;
+
+      try {
+        const data = await toScriptInWorker(payload.script, {
+          ...payload.current,
+        });
+        result = { data, status: "ok" };
+      } catch (err: any) {
+        result = { data: {}, status: "error", error: err.message };
+      }
+
+      emit(payload.callback, JSON.stringify(result)).catch(console.error);
+    });
   }
 
   // exec the listener
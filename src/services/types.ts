@@ -13,6 +13,8 @@ export namespace ApiType {
     "redir-port": number;
     "socks-port": number;
     "tproxy-port": number;
+    "interface-name"?: string;
+    "routing-mark"?: number;
   }
 
   export interface RuleItem {
@@ -78,7 +80,12 @@ export namespace ApiType {
  * Some interface for command
  */
 export namespace CmdType {
-  export type ProfileType = "local" | "remote" | "merge" | "script";
+  export type ProfileType =
+    | "local"
+    | "remote"
+    | "merge"
+    | "script"
+    | "file-link";
 
   export interface ClashInfo {
     status: string;
@@ -87,6 +94,86 @@ export namespace CmdType {
     secret?: string;
   }
 
+  export interface TrafficTotal {
+    uploadTotal: number;
+    downloadTotal: number;
+  }
+
+  export interface ClashVersion {
+    version: string;
+    premium: boolean;
+    meta: boolean;
+  }
+
+  export interface CoreStatus {
+    running: boolean;
+    port?: string;
+    responsive: boolean;
+  }
+
+  export interface Connectivity {
+    status?: number;
+    delay?: number;
+    error?: string;
+  }
+
+  export interface RuntimeConfig {
+    general: Partial<ApiType.ConfigData>;
+    rules: ApiType.RuleItem[];
+    file?: Record<string, any>;
+  }
+
+  export interface RuleOverrides {
+    "bypass-domains"?: string[];
+    "prepend-rules"?: string[];
+    "append-rules"?: string[];
+  }
+
+  export interface DnsAnswer {
+    name: string;
+    type: number;
+    TTL: number;
+    data: string;
+  }
+
+  export interface DirUsage {
+    path: string;
+    files: number;
+    bytes: number;
+  }
+
+  export interface StorageInfo {
+    profiles: DirUsage;
+    logs: DirUsage;
+  }
+
+  export interface ProviderInfo {
+    name: string;
+    kind: "proxy" | "rule";
+    vehicle_type: string;
+    updated_at?: string;
+  }
+
+  export interface SysProxyStatus {
+    enable: boolean;
+    server: string;
+    bypass: string;
+    pac_url?: string;
+    tampered: boolean;
+  }
+
+  export interface LaunchStatus {
+    enable: boolean;
+    registered?: string;
+    current: string;
+    matched: boolean;
+  }
+
+  export interface ConnectivityResult {
+    proxy: Connectivity;
+    direct: Connectivity;
+  }
+
   export interface ProfileItem {
     uid: string;
     type?: ProfileType | string;
@@ -94,7 +181,17 @@ export namespace CmdType {
     desc?: string;
     file?: string;
     url?: string;
+    home?: string;
+    auto_update?: boolean;
+    src_path?: string;
     updated?: number;
+    last_error?: string;
+    stats?: {
+      size: number;
+      proxies?: number;
+      rules?: number;
+      modified?: number;
+    };
     selected?: {
       name?: string;
       now?: string;
@@ -111,6 +208,25 @@ export namespace CmdType {
   export interface ProfileOption {
     user_agent?: string;
     with_proxy?: boolean;
+    update_interval?: number;
+    headers?: Record<string, string>;
+    timeout_secs?: number;
+    retries?: number;
+    save_redirect_url?: boolean;
+    with_basic_auth?: boolean;
+  }
+
+  export interface ProfileImportResult {
+    url: string;
+    name?: string;
+    uid?: string;
+    error?: string;
+  }
+
+  export interface ProfileUpdateResult {
+    uid: string;
+    name?: string;
+    error?: string;
   }
 
   export interface ProfilesConfig {
@@ -121,14 +237,35 @@ export namespace CmdType {
 
   export interface VergeConfig {
     language?: string;
-    theme_mode?: "light" | "dark";
+    theme_mode?: "light" | "dark" | "system";
     theme_blur?: boolean;
+    window_effect?: "blur" | "acrylic" | "none";
     traffic_graph?: boolean;
+    log_level?: "error" | "warn" | "info" | "debug" | "trace";
+    max_log_days?: number;
+    default_editor?: string;
+    dashboard_url?: string;
     enable_tun_mode?: boolean;
+    sysproxy_with_tun?: boolean;
+    auto_restart_core?: boolean;
+    auto_pick_ports?: boolean;
+    app_proxy?: string;
+    geo_data_url?: string;
+    register_scheme?: boolean;
+    enable_singleton?: boolean;
+    server_port?: number;
+    clash_mode?: "rule" | "global" | "direct" | "script";
     enable_auto_launch?: boolean;
+    silent_start?: boolean;
     enable_system_proxy?: boolean;
+    enable_kill_switch?: boolean;
     enable_proxy_guard?: boolean;
+    reset_proxy_on_exit?: boolean;
     system_proxy_bypass?: string;
+    system_proxy_protocol?: "all" | "http" | "socks";
+    proxy_mode?: "global" | "pac";
+    core?: "clash" | "clash-meta";
+    hotkeys?: Record<string, string>;
   }
 
   export type ProfileMerge = Record<string, any>;
@@ -154,6 +291,12 @@ export namespace CmdType {
     callback: string;
   }
 
+  export interface ScriptTestPayload {
+    current: ProfileData;
+    script: string;
+    callback: string;
+  }
+
   export interface EnhancedResult {
     data: ProfileData;
     status: string;